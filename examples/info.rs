@@ -7,6 +7,11 @@ fn main() {
     let log = Log::new_root(loga::INFO);
     log.log(loga::INFO, "Hello");
     log.log_with(loga::INFO, "Hello", ea!(xyz = "abc", system = "primary"));
+
+    // Named arguments are also recorded as attributes (inline captures like `{table}`
+    // without `table = table` are rejected)
+    let table = "users";
+    loga::info!(log, "Fetched {count} rows from {table}", count = 12, table = table; ea!(system = "primary"));
     log.log_err(
         loga::INFO,
         loga::err_with(
@@ -0,0 +1,21 @@
+use loga::{
+    ea,
+    LogfmtFormat,
+    Log,
+};
+
+fn main() {
+    let log = Log::new_root_with_format(loga::INFO, LogfmtFormat::new()).fork(ea!(system = "main"));
+    log.log_with(loga::INFO, "Hello", ea!(xyz = "abc \"quoted\"\nnewline"));
+    log.log_err(
+        loga::WARN,
+        loga::agg_err_with(
+            "This is a sub-error",
+            vec![loga::err("Problem 1"), loga::err_with("Problem 2", ea!(client = "a:b:c:d"))],
+            ea!(system = "secondary"),
+        )
+            .also(loga::err("Cleanup failed"))
+            .context_with("Got an error", ea!(context = "Additional details")),
+    );
+    log.fatal(log.err("Shutting down"));
+}
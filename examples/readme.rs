@@ -37,7 +37,7 @@ fn main1() -> Result<(), loga::Error> {
             let e = e.stack_context(log, "Failed to launch satellite");
             if let Err(e2) = shutdown_server() {
                 // Attach incidental errors
-                return Err(e.also(e2.into()));
+                return Err(e.also(e2));
             }
             return Err(e);
         },
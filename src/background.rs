@@ -0,0 +1,314 @@
+use {
+    crate::{
+        sink::{
+            Event,
+            EventNode,
+            Sink,
+        },
+        value::Value,
+        WARN,
+    },
+    std::{
+        collections::VecDeque,
+        sync::{
+            Arc,
+            Condvar,
+            Mutex,
+            Weak,
+        },
+        thread::JoinHandle,
+    },
+};
+
+/// What to do when an event is written to a `BackgroundSink` whose queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait for space in the queue.
+    Block,
+    /// Discard the new event.
+    DropNewest,
+    /// Discard the oldest event in the queue to make room for the new event.
+    DropOldest,
+}
+
+struct QueueState {
+    queue: VecDeque<Event>,
+    dropped: usize,
+    writing: bool,
+    shutdown: bool,
+}
+
+struct Shared {
+    inner: Box<dyn Sink>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+/// All live background sinks, see `flush_all`.
+static SINKS: Mutex<Vec<Weak<Shared>>> = Mutex::new(vec![]);
+
+struct BackgroundSink_ {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for BackgroundSink_ {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.changed.notify_all();
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+
+/// Wraps another sink, queueing events and writing them from a dedicated thread so
+/// rendering and output don't block the logging thread.
+///
+/// If any events are dropped due to the overflow policy, a warning event with the
+/// number of dropped events is written once there's room again.
+///
+/// Queued events are written before exiting in `fatal` or `Log::fatal`, and when
+/// the last clone of the sink is dropped.  Call `flush` or hold a guard from
+/// `flush_guard` to make sure events are written before exiting in other cases.
+#[derive(Clone)]
+pub struct BackgroundSink(Arc<BackgroundSink_>);
+
+impl BackgroundSink {
+    /// Start the writer thread. `capacity` is the maximum number of events that can be
+    /// queued before applying the overflow policy.
+    pub fn new(inner: impl Sink + 'static, capacity: usize, policy: OverflowPolicy) -> Self {
+        let shared = Arc::new(Shared {
+            inner: Box::new(inner),
+            capacity: capacity.max(1),
+            policy: policy,
+            state: Mutex::new(QueueState {
+                queue: VecDeque::new(),
+                dropped: 0,
+                writing: false,
+                shutdown: false,
+            }),
+            changed: Condvar::new(),
+        });
+        {
+            let mut sinks = SINKS.lock().unwrap();
+            sinks.retain(|s| s.strong_count() > 0);
+            sinks.push(Arc::downgrade(&shared));
+        }
+        let thread = std::thread::Builder::new().name("loga-writer".to_string()).spawn({
+            let shared = shared.clone();
+            move || writer(shared)
+        }).unwrap();
+        return Self(Arc::new(BackgroundSink_ {
+            shared: shared,
+            thread: Some(thread),
+        }));
+    }
+
+    /// Return a guard that flushes the sink when dropped.  Hold this in `main` to make
+    /// sure queued events are written before the program exits.
+    pub fn flush_guard(&self) -> FlushGuard {
+        return FlushGuard(self.clone());
+    }
+}
+
+impl Sink for BackgroundSink {
+    fn write(&self, event: &Event) {
+        let shared = &self.0.shared;
+        let mut state = shared.state.lock().unwrap();
+        if state.queue.len() >= shared.capacity {
+            match shared.policy {
+                OverflowPolicy::Block => {
+                    while state.queue.len() >= shared.capacity {
+                        state = shared.changed.wait(state).unwrap();
+                    }
+                },
+                OverflowPolicy::DropNewest => {
+                    state.dropped += 1;
+                    return;
+                },
+                OverflowPolicy::DropOldest => {
+                    state.queue.pop_front();
+                    state.dropped += 1;
+                },
+            }
+        }
+        state.queue.push_back(event.clone());
+        drop(state);
+        shared.changed.notify_all();
+    }
+
+    /// Wait until all queued events have been written, then flush the inner sink.
+    fn flush(&self) {
+        self.0.shared.flush();
+    }
+}
+
+impl Shared {
+    fn flush(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.queue.is_empty() || state.dropped > 0 || state.writing {
+            state = self.changed.wait(state).unwrap();
+        }
+        drop(state);
+        self.inner.flush();
+    }
+}
+
+/// Flush all live background sinks.  Used by `fatal` to write queued events before
+/// exiting, even for sinks not attached to the `Log` used.
+pub(crate) fn flush_all() {
+    let sinks = SINKS.lock().unwrap().iter().filter_map(|s| s.upgrade()).collect::<Vec<_>>();
+    for sink in sinks {
+        sink.flush();
+    }
+}
+
+fn writer(shared: Arc<Shared>) {
+    loop {
+        let mut state = shared.state.lock().unwrap();
+        while state.queue.is_empty() && state.dropped == 0 && !state.shutdown {
+            state = shared.changed.wait(state).unwrap();
+        }
+        if state.queue.is_empty() && state.dropped == 0 {
+            break;
+        }
+        let events = state.queue.drain(..).collect::<Vec<_>>();
+        let dropped = state.dropped;
+        state.dropped = 0;
+        state.writing = true;
+        drop(state);
+        shared.changed.notify_all();
+        let dropped_event = if dropped > 0 {
+            Some(Event::new(WARN, EventNode {
+                message: "Log events were dropped due to a full queue".to_string(),
+                template: None,
+                location: None,
+                backtrace: None,
+                attrs: vec![("count".into(), Value::U64(dropped as u64))],
+                causes: vec![],
+                incidental: vec![],
+            }))
+        } else {
+            None
+        };
+
+        // Write the dropped count in the approximate position of the dropped events
+        if shared.policy == OverflowPolicy::DropOldest {
+            if let Some(e) = &dropped_event {
+                shared.inner.write(e);
+            }
+        }
+        for event in events {
+            shared.inner.write(&event);
+        }
+        if shared.policy == OverflowPolicy::DropNewest {
+            if let Some(e) = &dropped_event {
+                shared.inner.write(e);
+            }
+        }
+        shared.state.lock().unwrap().writing = false;
+        shared.changed.notify_all();
+    }
+    shared.inner.flush();
+}
+
+/// Flushes a `BackgroundSink` when dropped.
+pub struct FlushGuard(BackgroundSink);
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        self.0.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            BackgroundSink,
+            OverflowPolicy,
+        },
+        crate::{
+            sink::{
+                Event,
+                EventNode,
+                Sink,
+            },
+            Captured,
+            INFO,
+        },
+        std::sync::{
+            Arc,
+            Mutex,
+        },
+    };
+
+    /// Captures events, but only while the gate isn't locked.
+    struct Gated {
+        gate: Arc<Mutex<()>>,
+        captured: Captured,
+    }
+
+    impl Sink for Gated {
+        fn write(&self, event: &Event) {
+            let _gate = self.gate.lock().unwrap();
+            self.captured.write(event);
+        }
+    }
+
+    /// Write events while the writer thread is stuck writing a first event, then
+    /// return the messages of all events written.
+    fn overflow(policy: OverflowPolicy, capacity: usize, count: usize) -> Vec<String> {
+        let gate = Arc::new(Mutex::new(()));
+        let captured = Captured::new();
+        let sink = BackgroundSink::new(Gated {
+            gate: gate.clone(),
+            captured: captured.clone(),
+        }, capacity, policy);
+        let locked = gate.lock().unwrap();
+        sink.write(&Event::new(INFO, EventNode::new_test("0", vec![], vec![])));
+        while !sink.0.shared.state.lock().unwrap().writing {
+            std::thread::yield_now();
+        }
+        let writer = std::thread::spawn({
+            let sink = sink.clone();
+            move || {
+                for i in 1 ..= count {
+                    sink.write(&Event::new(INFO, EventNode::new_test(&i.to_string(), vec![], vec![])));
+                }
+            }
+        });
+        if policy == OverflowPolicy::Block {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert!(!writer.is_finished());
+            drop(locked);
+            writer.join().unwrap();
+        } else {
+            writer.join().unwrap();
+            drop(locked);
+        }
+        sink.flush();
+        return captured.events().into_iter().map(|e| match e.node.attrs.first() {
+            Some((_, count)) => format!("dropped {}", count),
+            None => e.node.message,
+        }).collect();
+    }
+
+    #[test]
+    fn drop_newest() {
+        assert_eq!(overflow(OverflowPolicy::DropNewest, 2, 4), vec!["0", "1", "2", "dropped 2"]);
+    }
+
+    #[test]
+    fn drop_oldest() {
+        assert_eq!(overflow(OverflowPolicy::DropOldest, 2, 4), vec!["0", "dropped 2", "3", "4"]);
+    }
+
+    #[test]
+    fn block() {
+        assert_eq!(overflow(OverflowPolicy::Block, 2, 4), vec!["0", "1", "2", "3", "4"]);
+    }
+}
@@ -0,0 +1,219 @@
+use {
+    crate::{
+        sink::{
+            Event,
+            Sink,
+        },
+        value::{
+            ToValue,
+            Value,
+        },
+        Level,
+    },
+    std::sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+/// A sink that stores events in memory, for checking what was logged in tests.  See
+/// `Log::new_capture`.
+///
+/// Clones share the same event list.
+#[derive(Clone, Default)]
+pub struct Captured(Arc<Mutex<Vec<Event>>>);
+
+impl Captured {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// All events captured so far, in order.
+    pub fn events(&self) -> Vec<Event> {
+        return self.0.lock().unwrap().clone();
+    }
+
+    /// Discard all captured events.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    /// Start a query for captured events.  With no filters the query matches all
+    /// events.  Use like `captured.query().level(WARN).attr("user", "x").one()`.
+    pub fn query(&self) -> CaptureQuery<'_> {
+        return CaptureQuery {
+            captured: self,
+            level: None,
+            message: None,
+            attrs: vec![],
+        };
+    }
+}
+
+impl Sink for Captured {
+    fn write(&self, event: &Event) {
+        self.0.lock().unwrap().push(event.clone());
+    }
+}
+
+/// Filters for captured events.  All filters apply to the top level of the event;
+/// use `EventNode::shape` or the `causes` and `incidental` fields to check nested
+/// errors.
+pub struct CaptureQuery<'a> {
+    captured: &'a Captured,
+    level: Option<Level>,
+    message: Option<String>,
+    attrs: Vec<(String, Option<Value>)>,
+}
+
+impl<'a> CaptureQuery<'a> {
+    /// Match events at exactly this level.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        return self;
+    }
+
+    /// Match events whose message contains this text.
+    pub fn message(mut self, text: impl ToString) -> Self {
+        self.message = Some(text.to_string());
+        return self;
+    }
+
+    /// Match events with this attribute value (including attributes from the `Log`
+    /// context).  Numbers match if they're numerically equal, regardless of type.
+    pub fn attr(mut self, key: impl ToString, value: impl ToValue) -> Self {
+        self.attrs.push((key.to_string(), Some(value.into_value())));
+        return self;
+    }
+
+    /// Match events that have this attribute, with any value.
+    pub fn has_attr(mut self, key: impl ToString) -> Self {
+        self.attrs.push((key.to_string(), None));
+        return self;
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        if let Some(level) = self.level {
+            if event.level != level {
+                return false;
+            }
+        }
+        if let Some(message) = &self.message {
+            if !event.node.message.contains(message.as_str()) {
+                return false;
+            }
+        }
+        for (key, want_value) in &self.attrs {
+            let Some(value) = event.node.attr(key) else {
+                return false;
+            };
+            if let Some(want_value) = want_value {
+                let matches = match (value.as_f64(), want_value.as_f64()) {
+                    (Some(value), Some(want_value)) => value == want_value,
+                    _ => value == want_value,
+                };
+                if !matches {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    /// All matching events, in order.
+    pub fn all(&self) -> Vec<Event> {
+        return self.captured.0.lock().unwrap().iter().filter(|e| self.matches(e)).cloned().collect();
+    }
+
+    pub fn count(&self) -> usize {
+        return self.all().len();
+    }
+
+    pub fn exists(&self) -> bool {
+        return self.count() > 0;
+    }
+
+    /// Return the single matching event, panicking (listing all captured events) if
+    /// there isn't exactly one match.
+    #[track_caller]
+    pub fn one(&self) -> Event {
+        let mut found = self.all();
+        if found.len() != 1 {
+            panic!("Expected exactly one matching captured event but found {}\n{}", found.len(), self.describe());
+        }
+        return found.remove(0);
+    }
+
+    /// Panic (listing all captured events) if any events match.
+    #[track_caller]
+    pub fn assert_none(&self) {
+        let found = self.count();
+        if found != 0 {
+            panic!("Expected no matching captured events but found {}\n{}", found, self.describe());
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut out = format!(
+            "Query: level = {:?}, message contains {:?}, attrs = {:?}\nCaptured events:\n",
+            self.level.map(|l| l.name()),
+            self.message,
+            self.attrs
+        );
+        for event in self.captured.0.lock().unwrap().iter() {
+            out.push_str(&format!("- {}: {} {:?}\n", event.level.name(), event.node.shape(), event.node.attrs));
+        }
+        return out;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ea,
+        err,
+        info,
+        Log,
+        DEBUG,
+        ERR,
+        FATAL,
+        INFO,
+        WARN,
+    };
+
+    #[test]
+    fn query() {
+        let (log, captured) = Log::new_capture(INFO);
+        let log = log.fork(ea!(user = "x"));
+        info!(log, "Fetched {n} rows", n = 3);
+        log.log_with(WARN, "Slow query", ea!(ms = 1500u64));
+        log.log(DEBUG, "Not captured");
+        log.log_err(
+            ERR,
+            err("Connection lost").context("Error running query").also(err!("Error closing {}", "socket")),
+        );
+        assert_eq!(captured.events().len(), 3);
+        assert_eq!(captured.query().level(DEBUG).count(), 0);
+        assert_eq!(captured.query().message("rows").attr("n", 3i64).attr("user", "x").one().node.message, "Fetched 3 rows");
+        assert!(captured.query().attr("ms", 1500.).exists());
+        captured.query().attr("ms", 1).assert_none();
+        assert_eq!(
+            captured.query().level(ERR).has_attr("user").one().node.shape(),
+            "Error running query (caused by: Connection lost) (incidentally: Error closing socket)"
+        );
+        log.log(FATAL, "Not exiting");
+        assert!(!log.enabled(FATAL));
+        captured.query().level(FATAL).assert_none();
+        captured.clear();
+        captured.query().assert_none();
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected exactly one matching captured event but found 2")]
+    fn one_panics_on_multiple() {
+        let (log, captured) = Log::new_capture(INFO);
+        log.log(INFO, "Repeated");
+        log.log(INFO, "Repeated");
+        captured.query().message("Repeated").one();
+    }
+}
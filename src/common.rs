@@ -1,3 +1,11 @@
+use {
+    crate::value::{
+        ToValue,
+        Value,
+    },
+    std::borrow::Cow,
+};
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
 pub struct Level(pub(crate) i8);
 
@@ -5,18 +13,210 @@ pub const DEBUG: Level = Level(1);
 pub const INFO: Level = Level(2);
 pub const WARN: Level = Level(3);
 pub const ERR: Level = Level(4);
+/// The level of events output by `fatal`.  Events can't be logged at this level
+/// with `Log::log` etc. (they're discarded), only with `fatal` or `Log::fatal`,
+/// which also exit.  As the minimum level of a root or sink, only fatal errors are
+/// output.
+pub const FATAL: Level = Level(5);
+
+impl Level {
+    /// The label used when outputting events at this level.
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            1 => return "DEBUG",
+            2 => return "INFO",
+            3 => return "WARN",
+            4 => return "ERROR",
+            5 => return "FATAL",
+            _ => unreachable!(),
+        }
+    }
+
+    /// The syslog severity (also used as the journald priority) for this level.
+    pub(crate) fn syslog_severity(&self) -> u8 {
+        match self.0 {
+            1 => return 7,
+            2 => return 6,
+            3 => return 4,
+            4 => return 3,
+            5 => return 2,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Attributes attached to errors and logging contexts.  Attributes are kept in
+/// insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct Attrs(pub(crate) Vec<(Cow<'static, str>, Value)>);
+
+impl Attrs {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Set an attribute.  If the key is already set, the value is replaced but the
+    /// attribute keeps its original position.
+    pub fn insert(&mut self, key: impl Into<Cow<'static, str>>, value: impl ToValue) {
+        let key = key.into();
+        let value = value.into_value();
+        if let Some(existing) = self.0.iter_mut().find(|a| a.0 == key) {
+            existing.1 = value;
+        } else {
+            self.0.push((key, value));
+        }
+    }
+
+    /// Set a value within a nested map attribute.  `path` is the top level key
+    /// followed by keys within the map.  If the attribute or an intermediate value
+    /// exists but isn't a map it's replaced.
+    pub fn insert_nested(&mut self, path: &[&'static str], value: impl ToValue) {
+        let Some((first, rest)) = path.split_first() else {
+            return;
+        };
+        if rest.is_empty() {
+            self.insert(*first, value);
+            return;
+        }
+        let index = match self.0.iter().position(|a| a.0 == *first) {
+            Some(index) => index,
+            None => {
+                self.0.push((Cow::Borrowed(*first), Value::Map(vec![])));
+                self.0.len() - 1
+            },
+        };
+        insert_nested_value(&mut self.0[index].1, rest, value.into_value());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        return self.0.iter().find(|a| a.0 == key).map(|a| &a.1);
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (Cow<'static, str>, Value)> {
+        return self.0.iter();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.0.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.0.is_empty();
+    }
+}
+
+fn insert_nested_value(at: &mut Value, path: &[&'static str], value: Value) {
+    let Some((first, rest)) = path.split_first() else {
+        *at = value;
+        return;
+    };
+    if !matches!(at, Value::Map(_)) {
+        *at = Value::Map(vec![]);
+    }
+    let Value::Map(entries) = at else {
+        unreachable!();
+    };
+    let index = match entries.iter().position(|e| e.0 == *first) {
+        Some(index) => index,
+        None => {
+            entries.push((first.to_string(), Value::Null));
+            entries.len() - 1
+        },
+    };
+    insert_nested_value(&mut entries[index].1, rest, value);
+}
+
+impl<'a> IntoIterator for &'a Attrs {
+    type Item = &'a (Cow<'static, str>, Value);
+    type IntoIter = std::slice::Iter<'a, (Cow<'static, str>, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        return self.0.iter();
+    }
+}
 
 /// Turn key/values into a lambda for extending attributes, used in various log and
-/// error functions.
+/// error functions.  Values implementing `ToValue` (numbers, booleans, strings,
+/// lists, etc.) keep their type, other values implementing `Display` are converted
+/// to strings.
+///
+/// Keys can be identifiers (`ea!(key = value)`), string literals
+/// (`ea!("key-with-dashes" = value)`), or any expression implementing `Display` in
+/// brackets for keys determined at runtime (`ea!([header_name] = value)`).
+/// Dotted identifiers (`ea!(http.status = code)`) set a value within a nested map
+/// attribute, merging with other values in the same group.
+///
+/// Prefix values with `?` to use the `Debug` representation, `#?` for the pretty
+/// `Debug` representation, or `%` to use the `Display` representation for values
+/// that would otherwise be typed.
+///
+/// A variable name by itself (optionally with a `?` or `%` prefix) is shorthand for
+/// `name = name`: `ea!(user_id, ?request)`.
 #[macro_export]
 macro_rules! ea{
-    ($($k: ident = $v: expr), *) => {
+    ($($t: tt) *) => {
         | _attrs | {
-            $(_attrs.insert(stringify!($k), $v.to_string());) *
+            #[allow(unused_imports)]
+            use $crate::value::{
+                ViaDisplay as _,
+                ViaToValue as _,
+            };
+            $crate::__ea_insert!(_attrs; $($t) *);
         }
     };
 }
 
+/// Parse the key of the next `ea!` attribute.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ea_insert{
+    ($attrs: ident;) => { };
+    ($attrs: ident; $k: ident $(.$k2: ident) + = $($rest: tt) *) => {
+        $crate::__ea_value!($attrs; insert_nested;(&[stringify!($k) $(, stringify!($k2)) +]); $($rest) *);
+    };
+    ($attrs: ident; $k: ident = $($rest: tt) *) => {
+        $crate::__ea_value!($attrs; insert;(stringify!($k)); $($rest) *);
+    };
+    ($attrs: ident; $k: literal = $($rest: tt) *) => {
+        $crate::__ea_value!($attrs; insert;($k); $($rest) *);
+    };
+    ($attrs: ident;[$k: expr] = $($rest: tt) *) => {
+        $crate::__ea_value!($attrs; insert;(::std::string::ToString::to_string(&$k)); $($rest) *);
+    };
+    ($attrs: ident; ? $k: ident $(, $($rest: tt) *)?) => {
+        $crate::__ea_value!($attrs; insert;(stringify!($k)); ? $k $(, $($rest) *)?);
+    };
+    ($attrs: ident; % $k: ident $(, $($rest: tt) *)?) => {
+        $crate::__ea_value!($attrs; insert;(stringify!($k)); % $k $(, $($rest) *)?);
+    };
+    ($attrs: ident; $k: ident $(, $($rest: tt) *)?) => {
+        $crate::__ea_value!($attrs; insert;(stringify!($k)); $k $(, $($rest) *)?);
+    };
+}
+
+/// Parse the value of the next `ea!` attribute and insert it, then continue with
+/// the remaining attributes.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ea_value{
+    ($attrs: ident; $method: ident;($($k: tt) *); #? $v: expr $(, $($rest: tt) *)?) => {
+        $attrs.$method($($k) *, $crate::Value::Str(::std::format!("{:#?}", $v)));
+        $crate::__ea_insert!($attrs; $($($rest) *)?);
+    };
+    ($attrs: ident; $method: ident;($($k: tt) *); ? $v: expr $(, $($rest: tt) *)?) => {
+        $attrs.$method($($k) *, $crate::Value::Str(::std::format!("{:?}", $v)));
+        $crate::__ea_insert!($attrs; $($($rest) *)?);
+    };
+    ($attrs: ident; $method: ident;($($k: tt) *); % $v: expr $(, $($rest: tt) *)?) => {
+        $attrs.$method($($k) *, $crate::Value::Str(::std::format!("{}", $v)));
+        $crate::__ea_insert!($attrs; $($($rest) *)?);
+    };
+    ($attrs: ident; $method: ident;($($k: tt) *); $v: expr $(, $($rest: tt) *)?) => {
+        $attrs.$method($($k) *, (&$crate::value::ValueWrap(&$v)).loga_value());
+        $crate::__ea_insert!($attrs; $($($rest) *)?);
+    };
+}
+
 /// A helper to easily generate debug strings for types implementing `Debug`.
 pub trait DebugDisplay {
     fn dbg_str(&self) -> String;
@@ -32,3 +232,61 @@ impl<T: std::fmt::Debug> DebugDisplay for T {
         return format!("{:#?}", self);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Attrs,
+        Value,
+    };
+
+    fn build(f: impl Fn(&mut Attrs)) -> Vec<(String, Value)> {
+        let mut attrs = Attrs::new();
+        f(&mut attrs);
+        return attrs.into_iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+    }
+
+    #[test]
+    fn ea_syntax() {
+        let user_id = 7u64;
+        let point = Some((1, 2));
+        let header = "x-request-id";
+        let path = std::path::Path::new("/tmp");
+        assert_eq!(
+            build(ea!(
+                count = 3,
+                "with-dashes" = true,
+                [header] = "abc",
+                user_id,
+                ?point,
+                debug = ?point,
+                pretty = #?point,
+                text = %5,
+                path = path.display(),
+                http.status = 200,
+                http.method = "GET"
+            )),
+            vec![
+                ("count".to_string(), Value::I64(3)),
+                ("with-dashes".to_string(), Value::Bool(true)),
+                ("x-request-id".to_string(), Value::Str("abc".into())),
+                ("user_id".to_string(), Value::U64(7)),
+                ("point".to_string(), Value::Str("Some((1, 2))".into())),
+                ("debug".to_string(), Value::Str("Some((1, 2))".into())),
+                ("pretty".to_string(), Value::Str("Some(\n    (\n        1,\n        2,\n    ),\n)".into())),
+                ("text".to_string(), Value::Str("5".into())),
+                ("path".to_string(), Value::Str("/tmp".into())),
+                (
+                    "http".to_string(),
+                    Value::Map(vec![("status".into(), Value::I64(200)), ("method".into(), Value::Str("GET".into()))]),
+                )
+            ]
+        );
+        assert_eq!(build(ea!()), vec![]);
+    }
+
+    #[test]
+    fn ea_overwrite() {
+        assert_eq!(build(ea!(a = 1, b = 2, a = 3)), vec![("a".to_string(), Value::I64(3)), ("b".to_string(), Value::I64(2))]);
+    }
+}
@@ -1,7 +1,5 @@
-use std::{
-    collections::HashMap,
-};
 use crate::{
+    common::Attrs,
     entry::{
         err,
         err_with,
@@ -13,6 +11,7 @@ use crate::{
     Level,
     Log,
 };
+use std::panic::Location;
 
 /// A trait adding helper methods to standard errors to convert to `loga::Error`.
 pub trait ErrContext {
@@ -22,7 +21,7 @@ pub trait ErrContext {
 
     /// Add a simple context string and attributes pairs onto an error, converting it
     /// to `loga::Error` in the process.
-    fn context_with(self, message: impl ToString, attrs: impl Fn(&mut HashMap<&'static str, String>) -> ()) -> Error;
+    fn context_with(self, message: impl ToString, attrs: impl Fn(&mut Attrs) -> ()) -> Error;
 
     /// Add attributes from the log as well as a simple context string to an error,
     /// converting it to `loga::Error` in the process.
@@ -34,27 +33,33 @@ pub trait ErrContext {
         self,
         log: &Log,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Error;
 }
 
 impl<T: Into<Error>> ErrContext for T {
+    #[track_caller]
     fn context(self, message: impl ToString) -> Error {
         return Error(Box::new(Error_ {
             message: message.to_string(),
-            attrs: HashMap::new(),
+            attrs: Attrs::new(),
             context: vec![],
             causes: vec![self.into()],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
+    #[track_caller]
     fn context_with(
         self,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Error {
-        let mut new_attrs = HashMap::new();
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Error(Box::new(Error_ {
             message: message.to_string(),
@@ -62,26 +67,36 @@ impl<T: Into<Error>> ErrContext for T {
             context: vec![],
             causes: vec![self.into()],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
+    #[track_caller]
     fn stack_context(self, log: &Log, message: impl ToString) -> Error {
         return Error(Box::new(Error_ {
             message: message.to_string(),
-            attrs: HashMap::new(),
+            attrs: Attrs::new(),
             context: vec![log.clone()],
             causes: vec![self.into()],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
+    #[track_caller]
     fn stack_context_with(
         self,
         log: &Log,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Error {
-        let mut new_attrs = HashMap::new();
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Error(Box::new(Error_ {
             message: message.to_string(),
@@ -89,6 +104,10 @@ impl<T: Into<Error>> ErrContext for T {
             context: vec![log.clone()],
             causes: vec![self.into()],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 }
@@ -105,7 +124,7 @@ pub trait ResultContext<O> {
     fn context_with(
         self,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Result<O, Error>;
 
     /// If the value is Err/None, add attributes from the log as well as a simple
@@ -119,7 +138,7 @@ pub trait ResultContext<O> {
         self,
         log: &Log,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Result<O, Error>;
 
     /// If this is an error and the argument result is an error, attach the argument
@@ -138,11 +157,12 @@ pub trait ResultContext<O> {
         log: &Log,
         level: Level,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     );
 }
 
 impl<O, E: Into<Error>> ResultContext<O> for Result<O, E> {
+    #[track_caller]
     fn context(self, message: impl ToString) -> Result<O, Error> {
         match self {
             Ok(x) => Ok(x),
@@ -150,10 +170,11 @@ impl<O, E: Into<Error>> ResultContext<O> for Result<O, E> {
         }
     }
 
+    #[track_caller]
     fn context_with(
         self,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Result<O, Error> {
         match self {
             Ok(x) => Ok(x),
@@ -161,6 +182,7 @@ impl<O, E: Into<Error>> ResultContext<O> for Result<O, E> {
         }
     }
 
+    #[track_caller]
     fn stack_context(self, log: &Log, message: impl ToString) -> Result<O, Error> {
         match self {
             Ok(x) => Ok(x),
@@ -168,11 +190,12 @@ impl<O, E: Into<Error>> ResultContext<O> for Result<O, E> {
         }
     }
 
+    #[track_caller]
     fn stack_context_with(
         self,
         log: &Log,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Result<O, Error> {
         match self {
             Ok(x) => Ok(x),
@@ -180,6 +203,7 @@ impl<O, E: Into<Error>> ResultContext<O> for Result<O, E> {
         }
     }
 
+    #[track_caller]
     fn also<O2, E2: Into<Error>>(self, r: Result<O2, E2>) -> Result<O, Error> {
         match self {
             Ok(o) => match r {
@@ -197,18 +221,20 @@ impl<O, E: Into<Error>> ResultContext<O> for Result<O, E> {
         }
     }
 
+    #[track_caller]
     fn log(self, log: &Log, level: Level, message: impl ToString) {
         if let Err(e) = self.context(message) {
             log.log_err(level, e);
         }
     }
 
+    #[track_caller]
     fn log_with(
         self,
         log: &Log,
         level: Level,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) {
         if let Err(e) = self.context_with(message, attrs) {
             log.log_err(level, e);
@@ -217,6 +243,7 @@ impl<O, E: Into<Error>> ResultContext<O> for Result<O, E> {
 }
 
 impl<O> ResultContext<O> for Option<O> {
+    #[track_caller]
     fn context(self, message: impl ToString) -> Result<O, Error> {
         match self {
             Some(x) => Ok(x),
@@ -224,10 +251,11 @@ impl<O> ResultContext<O> for Option<O> {
         }
     }
 
+    #[track_caller]
     fn context_with(
         self,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Result<O, Error> {
         match self {
             Some(x) => Ok(x),
@@ -235,6 +263,7 @@ impl<O> ResultContext<O> for Option<O> {
         }
     }
 
+    #[track_caller]
     fn stack_context(self, log: &Log, message: impl ToString) -> Result<O, Error> {
         match self {
             Some(x) => Ok(x),
@@ -242,11 +271,12 @@ impl<O> ResultContext<O> for Option<O> {
         }
     }
 
+    #[track_caller]
     fn stack_context_with(
         self,
         log: &Log,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Result<O, Error> {
         match self {
             Some(x) => Ok(x),
@@ -254,6 +284,7 @@ impl<O> ResultContext<O> for Option<O> {
         }
     }
 
+    #[track_caller]
     fn also<O2, E2: Into<Error>>(self, r: Result<O2, E2>) -> Result<O, Error> {
         match self {
             Some(o) => match r {
@@ -271,18 +302,20 @@ impl<O> ResultContext<O> for Option<O> {
         }
     }
 
+    #[track_caller]
     fn log(self, log: &Log, level: Level, message: impl ToString) {
         if self.is_none() {
             log.log_err(level, err("No value").context(message));
         }
     }
 
+    #[track_caller]
     fn log_with(
         self,
         log: &Log,
         level: Level,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) {
         if self.is_none() {
             log.log_err(level, err("No value").context_with(message, attrs));
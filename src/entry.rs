@@ -1,34 +1,36 @@
-use console::Style as TextStyle;
-use std::{
-    collections::{
-        HashMap,
-        HashSet,
+use std::panic::Location;
+use crate::{
+    common::Attrs,
+    types::{
+        capture_backtrace,
+        fatal_with_root,
+        Error,
+        Error_,
     },
-    io::Write,
-    process::exit,
-};
-use crate::types::{
-    log,
-    Error,
-    Error_,
 };
 
 /// Create a new error. If you want to inherit attributes from a logging context,
 /// see `Log::err`.
+#[track_caller]
 pub fn err(message: impl ToString) -> Error {
     return Error(Box::new(Error_ {
         message: message.to_string(),
-        attrs: HashMap::new(),
+        attrs: Attrs::new(),
         context: vec![],
         causes: vec![],
         incidental: vec![],
+        template: None,
+        location: Some(Location::caller()),
+        backtrace: capture_backtrace(),
+        original: None,
     }));
 }
 
 /// Create a new error and attach attributes. If you want to inherit attributes
 /// from a logging context, see `Log::err`.
-pub fn err_with(message: impl ToString, attrs: impl Fn(&mut HashMap<&'static str, String>) -> ()) -> Error {
-    let mut new_attrs = HashMap::new();
+#[track_caller]
+pub fn err_with(message: impl ToString, attrs: impl Fn(&mut Attrs) -> ()) -> Error {
+    let mut new_attrs = Attrs::new();
     attrs(&mut new_attrs);
     return Error(Box::new(Error_ {
         message: message.to_string(),
@@ -36,27 +38,37 @@ pub fn err_with(message: impl ToString, attrs: impl Fn(&mut HashMap<&'static str
         context: vec![],
         causes: vec![],
         incidental: vec![],
+        template: None,
+        location: Some(Location::caller()),
+        backtrace: capture_backtrace(),
+        original: None,
     }));
 }
 
 /// Create an error from multiple errors
+#[track_caller]
 pub fn agg_err(message: impl ToString, errs: Vec<Error>) -> Error {
     return Error(Box::new(Error_ {
         message: message.to_string(),
-        attrs: HashMap::new(),
+        attrs: Attrs::new(),
         context: vec![],
         causes: errs,
         incidental: vec![],
+        template: None,
+        location: Some(Location::caller()),
+        backtrace: None,
+        original: None,
     }));
 }
 
 /// Create an error from multiple errors, attaching attributes
+#[track_caller]
 pub fn agg_err_with(
     message: impl ToString,
     errs: Vec<Error>,
-    attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+    attrs: impl Fn(&mut Attrs) -> (),
 ) -> Error {
-    let mut new_attrs = HashMap::new();
+    let mut new_attrs = Attrs::new();
     attrs(&mut new_attrs);
     return Error(Box::new(Error_ {
         message: message.to_string(),
@@ -64,16 +76,17 @@ pub fn agg_err_with(
         context: vec![],
         causes: errs,
         incidental: vec![],
+        template: None,
+        location: Some(Location::caller()),
+        backtrace: None,
+        original: None,
     }));
 }
 
-/// Log a fatal error and terminate the program.
+/// Log a fatal error and terminate the program.  The error is output to the sinks
+/// of the first rooted `Log` it (or a cause) was created with, like `Log::fatal`,
+/// or to stderr as text if it has none.  Events queued in any `BackgroundSink` are
+/// written first.
 pub fn fatal(e: Error) -> ! {
-    let body_color = TextStyle::new().for_stderr().red();
-    let level_color = TextStyle::new().for_stderr().red().bold();
-    let mut node = e.build_render_nodes(&HashSet::new());
-    node.title = format!("Exiting due to error: {}", node.title).into();
-    log(body_color, level_color, "FATAL", node);
-    _ = std::io::stderr().flush();
-    exit(1)
+    fatal_with_root(e.find_root(), &e)
 }
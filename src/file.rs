@@ -0,0 +1,346 @@
+use {
+    crate::{
+        conversion::ResultContext,
+        format::Format,
+        sink::{
+            Event,
+            Sink,
+        },
+        Error,
+    },
+    chrono::{
+        DateTime,
+        Local,
+    },
+    std::{
+        fs::{
+            self,
+            File,
+            OpenOptions,
+        },
+        io::Write,
+        path::{
+            Path,
+            PathBuf,
+        },
+        sync::{
+            Arc,
+            Mutex,
+        },
+    },
+};
+
+/// When to start a new log file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    /// Never rotate.
+    Never,
+    /// Rotate before a write would make the file larger than this many bytes.
+    Size(u64),
+    /// Rotate on the first write after midnight (local time).
+    Daily,
+    /// Rotate on the first write after the start of each hour (local time).
+    Hourly,
+}
+
+impl Rotation {
+    /// A key identifying the rotation period containing the time, or `None` if
+    /// rotation isn't time based.
+    fn period(&self, time: DateTime<Local>) -> Option<String> {
+        match self {
+            Rotation::Never | Rotation::Size(_) => return None,
+            Rotation::Daily => return Some(time.format("%Y-%m-%d").to_string()),
+            Rotation::Hourly => return Some(time.format("%Y-%m-%dT%H").to_string()),
+        }
+    }
+}
+
+struct FileState {
+    file: Option<File>,
+    size: u64,
+    period: Option<String>,
+    /// Compression of the last rotated file, running in the background.
+    #[cfg(feature = "gzip")]
+    compressing: Option<std::thread::JoinHandle<()>>,
+}
+
+/// Outputs events to a file in the specified format, with optional rotation.
+///
+/// When rotated, the current file `path` is renamed to `path.1`, the previous
+/// `path.1` is renamed to `path.2`, etc. and files past the retention count are
+/// deleted.
+///
+/// Clones of the sink share the same file, so you can keep a clone to call
+/// `reopen` after creating a `Log` with it.
+#[derive(Clone)]
+pub struct FileSink {
+    path: PathBuf,
+    format: Arc<dyn Format>,
+    rotation: Rotation,
+    keep: usize,
+    #[cfg(feature = "gzip")]
+    compress: bool,
+    state: Arc<Mutex<FileState>>,
+}
+
+impl FileSink {
+    /// Create a sink appending to the file at `path`, creating it if it doesn't exist.
+    /// For `TextFormat` you probably want to disable colors with `with_color(false)`.
+    pub fn new(path: impl AsRef<Path>, format: impl Format + 'static) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut state = FileState {
+            file: None,
+            size: 0,
+            period: None,
+            #[cfg(feature = "gzip")]
+            compressing: None,
+        };
+        open(&path, &mut state)?;
+        return Ok(Self {
+            path: path,
+            format: Arc::new(format),
+            rotation: Rotation::Never,
+            keep: 5,
+            #[cfg(feature = "gzip")]
+            compress: false,
+            state: Arc::new(Mutex::new(state)),
+        });
+    }
+
+    /// Set when to rotate the file, defaults to `Rotation::Never`.
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        let mut state = self.state.lock().unwrap();
+        state.period = None;
+        if let Some(file) = &state.file {
+            if let Ok(modified) = file.metadata().and_then(|m| m.modified()) {
+                state.period = rotation.period(modified.into());
+            }
+        }
+        drop(state);
+        return self;
+    }
+
+    /// Set how many rotated files to keep, defaults to 5.
+    pub fn with_keep(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        return self;
+    }
+
+    /// Gzip rotated files (adding the extension `.gz`).  Compression happens on a
+    /// separate thread so writes aren't blocked; `flush` waits for it to finish.
+    #[cfg(feature = "gzip")]
+    pub fn with_compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        return self;
+    }
+
+    /// Close and reopen the file, for example after an external tool moved it (like
+    /// you'd do on `SIGHUP`).
+    pub fn reopen(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.file = None;
+        return open(&self.path, &mut state);
+    }
+
+    /// Rotate the file now, regardless of the rotation setting.
+    pub fn rotate(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        return self.rotate_locked(&mut state);
+    }
+
+    fn rotated_path(&self, index: usize, compressed: bool) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(format!(".{}", index));
+        if compressed {
+            name.push(".gz");
+        }
+        return PathBuf::from(name);
+    }
+
+    fn rotate_locked(&self, state: &mut FileState) -> Result<(), Error> {
+        state.file = None;
+
+        // The previous rotated file must be finished before it's renamed
+        #[cfg(feature = "gzip")]
+        if let Some(compressing) = state.compressing.take() {
+            _ = compressing.join();
+        }
+        for compressed in [false, true] {
+            let oldest = self.rotated_path(self.keep, compressed);
+            if oldest.exists() {
+                _ = fs::remove_file(oldest);
+            }
+        }
+        for i in (1 .. self.keep).rev() {
+            for compressed in [false, true] {
+                let from = self.rotated_path(i, compressed);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(i + 1, compressed))
+                        .context_with("Error renaming rotated log file", |attrs| {
+                            attrs.insert("path", from.to_string_lossy().to_string());
+                        })?;
+                }
+            }
+        }
+        if self.keep > 0 {
+            let rotated = self.rotated_path(1, false);
+            fs::rename(&self.path, &rotated).context("Error renaming log file for rotation")?;
+            #[cfg(feature = "gzip")]
+            if self.compress {
+                let compressed = self.rotated_path(1, true);
+                state.compressing = Some(std::thread::spawn(move || {
+                    _ = compress(&rotated, &compressed);
+                }));
+            }
+        } else {
+            fs::remove_file(&self.path).context("Error removing log file for rotation")?;
+        }
+        return open(&self.path, state);
+    }
+}
+
+fn open(path: &Path, state: &mut FileState) -> Result<(), Error> {
+    let file = OpenOptions::new().create(true).append(true).open(path).context_with("Error opening log file", |attrs| {
+        attrs.insert("path", path.to_string_lossy().to_string());
+    })?;
+    state.size = file.metadata().context("Error reading log file metadata")?.len();
+    state.file = Some(file);
+    return Ok(());
+}
+
+#[cfg(feature = "gzip")]
+fn compress(from: &Path, to: &Path) -> Result<(), Error> {
+    let mut source = File::open(from).context("Error opening rotated log file for compression")?;
+    let mut dest =
+        flate2::write::GzEncoder::new(
+            File::create(to).context("Error creating compressed log file")?,
+            flate2::Compression::default(),
+        );
+    std::io::copy(&mut source, &mut dest).context("Error compressing rotated log file")?;
+    dest.finish().context("Error finishing compressed log file")?;
+    fs::remove_file(from).context("Error removing uncompressed rotated log file")?;
+    return Ok(());
+}
+
+impl Sink for FileSink {
+    fn write(&self, event: &Event) {
+        let mut out = String::new();
+        self.format.render(event, &mut out);
+        let mut state = self.state.lock().unwrap();
+        let rotate = match self.rotation {
+            Rotation::Never => false,
+            Rotation::Size(max) => state.size > 0 && state.size + out.len() as u64 > max,
+            Rotation::Daily | Rotation::Hourly => {
+                let period = self.rotation.period(event.time.with_timezone(&Local));
+                let rotate = state.period.is_some() && state.period != period;
+                state.period = period;
+                rotate
+            },
+        };
+        if rotate || state.file.is_none() {
+            let res;
+            if rotate {
+                res = self.rotate_locked(&mut state);
+            } else {
+                res = open(&self.path, &mut state);
+            }
+            if res.is_err() {
+                return;
+            }
+        }
+        let Some(file) = state.file.as_mut() else {
+            return;
+        };
+        if file.write_all(out.as_bytes()).is_ok() {
+            state.size += out.len() as u64;
+        }
+    }
+
+    fn flush(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(file) = state.file.as_mut() {
+            _ = file.flush();
+        }
+        #[cfg(feature = "gzip")]
+        {
+            let compressing = state.compressing.take();
+            drop(state);
+            if let Some(compressing) = compressing {
+                _ = compressing.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            FileSink,
+            Rotation,
+        },
+        crate::{
+            logfmt::LogfmtFormat,
+            sink::{
+                Event,
+                EventNode,
+                Sink,
+            },
+            INFO,
+        },
+        std::fs,
+    };
+
+    #[test]
+    fn rotate_size() {
+        let dir = std::env::temp_dir().join(format!("loga-file-test-size-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        let sink = FileSink::new(&path, LogfmtFormat::new()).unwrap().with_rotation(Rotation::Size(10)).with_keep(1);
+        sink.write(&Event::new(INFO, EventNode::new_test("First", vec![], vec![])));
+        sink.write(&Event::new(INFO, EventNode::new_test("Second", vec![], vec![])));
+        sink.write(&Event::new(INFO, EventNode::new_test("Third", vec![], vec![])));
+        sink.flush();
+        let current = fs::read_to_string(&path).unwrap();
+        let rotated = fs::read_to_string(dir.join("log.1")).unwrap();
+        let dropped = dir.join("log.2").exists();
+        _ = fs::remove_dir_all(&dir);
+        assert!(current.contains("msg=Third"));
+        assert!(rotated.contains("msg=Second"));
+        assert!(!dropped);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn rotate_compressed() {
+        use std::io::Read;
+
+        let dir = std::env::temp_dir().join(format!("loga-file-test-gzip-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        let sink = FileSink::new(&path, LogfmtFormat::new()).unwrap().with_compress(true);
+        sink.write(&Event::new(INFO, EventNode::new_test("First", vec![], vec![])));
+        sink.rotate().unwrap();
+        sink.write(&Event::new(INFO, EventNode::new_test("Second", vec![], vec![])));
+        sink.rotate().unwrap();
+        sink.write(&Event::new(INFO, EventNode::new_test("Third", vec![], vec![])));
+        sink.flush();
+        let read_gz = |name: &str| {
+            let mut out = String::new();
+            flate2::read::GzDecoder::new(fs::File::open(dir.join(name)).unwrap()).read_to_string(&mut out).unwrap();
+            out
+        };
+        let current = fs::read_to_string(&path).unwrap();
+        let first = read_gz("log.2.gz");
+        let second = read_gz("log.1.gz");
+        let uncompressed = [dir.join("log.1").exists(), dir.join("log.2").exists()];
+        _ = fs::remove_dir_all(&dir);
+        assert!(current.contains("msg=Third"));
+        assert!(first.contains("msg=First"));
+        assert!(second.contains("msg=Second"));
+        assert_eq!(uncompressed, [false, false]);
+    }
+}
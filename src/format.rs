@@ -0,0 +1,261 @@
+use {
+    crate::{
+        sink::{
+            Event,
+            EventNode,
+        },
+        DEBUG,
+        ERR,
+        FATAL,
+        INFO,
+        WARN,
+    },
+    console::{
+        measure_text_width,
+        Style,
+    },
+    std::{
+        backtrace::Backtrace,
+        panic::Location,
+    },
+    textwrap::{
+        wrap,
+        Options,
+    },
+};
+
+/// Renders events to text for sinks that output to byte streams.
+pub trait Format: Send + Sync {
+    /// Append the rendered event to `out`, including any trailing newline.
+    fn render(&self, event: &Event, out: &mut String);
+}
+
+/// The default format, renders events as colored, word-wrapped trees for reading
+/// in a terminal.
+#[derive(Default)]
+pub struct TextFormat {
+    color: Option<bool>,
+    width: Option<usize>,
+}
+
+impl TextFormat {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Force colors on or off.  By default colors are used if stderr is a terminal.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = Some(color);
+        return self;
+    }
+
+    /// Wrap text at a fixed width.  By default text is wrapped at the terminal width.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        return self;
+    }
+
+    fn wrap_options<'a>(&self) -> Options<'a> {
+        match self.width {
+            Some(width) => return Options::new(width),
+            None => return Options::with_termwidth(),
+        }
+    }
+
+    fn style(&self) -> Style {
+        let style = Style::new().for_stderr();
+        match self.color {
+            Some(color) => return style.force_styling(color),
+            None => return style,
+        }
+    }
+}
+
+impl Format for TextFormat {
+    fn render(&self, event: &Event, out: &mut String) {
+        let body_style;
+        let label_style;
+        let title;
+        match event.level {
+            DEBUG => {
+                body_style = self.style().black().bright();
+                label_style = self.style().black().bright();
+            },
+            INFO => {
+                body_style = self.style().black();
+                label_style = self.style().black();
+            },
+            WARN => {
+                body_style = self.style().black();
+                label_style = self.style().yellow();
+            },
+            ERR => {
+                body_style = self.style().black();
+                label_style = self.style().red();
+            },
+            FATAL => {
+                body_style = self.style().red();
+                label_style = self.style().red().bold();
+            },
+            _ => unreachable!(),
+        }
+        if event.level == FATAL {
+            title = format!("Exiting due to error: {}", event.node.message);
+        } else {
+            title = event.node.message.clone();
+        }
+        out.push_str(
+            &format!(
+                "{} {}: {}\n",
+                body_style.apply_to(event.time.to_rfc3339()),
+                label_style.apply_to(event.level.name()),
+                title
+            ),
+        );
+        let highlight_style = self.style().blue();
+        let dark_style = self.style().dim();
+        let mut stack = vec![];
+        push_children(&mut stack, 0, &event.node);
+        while let Some((indent_count, top)) = stack.pop() {
+            let indent = "  ".repeat(indent_count);
+            match top {
+                TextLine::KV(key, value) => {
+                    let key = format!("- {} = ", key);
+                    for line in wrap(
+                        &value,
+                        self.wrap_options()
+                            .initial_indent(&format!("{}{}", indent, key))
+                            .subsequent_indent(&format!("{}{}", indent, " ".repeat(measure_text_width(&key)))),
+                    ) {
+                        out.push_str(&dark_style.apply_to(line).to_string());
+                        out.push('\n');
+                    }
+                },
+                TextLine::Location(location) => {
+                    out.push_str(&dark_style.apply_to(format!("{}at {}:{}", indent, location.file(), location.line())).to_string());
+                    out.push('\n');
+                },
+                TextLine::Title(title) => {
+                    for line in wrap(title, self.wrap_options().initial_indent(&indent).subsequent_indent(&indent)) {
+                        out.push_str(&highlight_style.apply_to(line).to_string());
+                        out.push('\n');
+                    }
+                },
+                TextLine::Node(node) => {
+                    for line in wrap(
+                        &node.message,
+                        self.wrap_options().initial_indent(&indent).subsequent_indent(&indent),
+                    ) {
+                        out.push_str(&highlight_style.apply_to(line).to_string());
+                        out.push('\n');
+                    }
+                    push_children(&mut stack, indent_count + 1, node);
+                },
+            }
+        }
+        let mut backtraces = vec![];
+        if event.level == FATAL {
+            collect_backtraces(&mut backtraces, &event.node);
+        }
+        for backtrace in backtraces {
+            out.push_str(&highlight_style.apply_to("Backtrace:").to_string());
+            out.push('\n');
+            for line in backtrace.to_string().lines() {
+                out.push_str(&dark_style.apply_to(line).to_string());
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// Find the backtraces in the tree, outermost first.
+fn collect_backtraces<'a>(out: &mut Vec<&'a Backtrace>, node: &'a EventNode) {
+    if let Some(backtrace) = &node.backtrace {
+        out.push(backtrace);
+    }
+    for child in node.causes.iter().chain(node.incidental.iter()) {
+        collect_backtraces(out, child);
+    }
+}
+
+enum TextLine<'a> {
+    KV(&'a str, String),
+    Location(&'static Location<'static>),
+    Title(&'static str),
+    Node(&'a EventNode),
+}
+
+/// Push the lines for a node's children onto the render stack, in reverse order
+/// (so they pop in order).
+fn push_children<'a>(stack: &mut Vec<(usize, TextLine<'a>)>, indent_count: usize, node: &'a EventNode) {
+    if !node.incidental.is_empty() {
+        stack.extend(node.incidental.iter().rev().map(|e| (indent_count + 1, TextLine::Node(e))));
+        stack.push((indent_count, TextLine::Title("Incidentally:")));
+    }
+    if !node.causes.is_empty() {
+        stack.extend(node.causes.iter().rev().map(|e| (indent_count + 1, TextLine::Node(e))));
+        stack.push((indent_count, TextLine::Title("Caused by:")));
+    }
+    stack.extend(node.attrs.iter().rev().map(|(k, v)| (indent_count, TextLine::KV(k, v.to_string()))));
+    if let Some(location) = node.location {
+        stack.push((indent_count, TextLine::Location(location)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            Format,
+            TextFormat,
+        },
+        crate::{
+            ea,
+            err_with,
+            Captured,
+            Log,
+            INFO,
+            WARN,
+        },
+        chrono::DateTime,
+    };
+
+    #[test]
+    fn text_snapshot() {
+        let captured = Captured::new();
+        let log =
+            Log::new_root_builder()
+                .sink(INFO, captured.clone())
+                .clock(|| DateTime::parse_from_rfc3339("2024-01-02T03:04:05+00:00").unwrap())
+                .build()
+                .fork(ea!(service = "api"));
+        log.log_with(INFO, "Started listening", ea!(port = 8080));
+        log.log_err(
+            WARN,
+            err_with("Error handling request", ea!(path = "/a/rather/long/path and some more words")).also(
+                err_with("Error closing connection after the request failed", ea!(peer = "a")),
+            ),
+        );
+        let format = TextFormat::new().with_color(false).with_width(40);
+        let mut out = String::new();
+        for event in captured.events() {
+            format.render(&event, &mut out);
+        }
+        assert_eq!(
+            out,
+            concat!(
+                "2024-01-02T03:04:05+00:00 INFO: Started listening\n",
+                "- port = 8080\n",
+                "- service = api\n",
+                "2024-01-02T03:04:05+00:00 WARN: Error handling request\n",
+                "- path = /a/rather/long/path and some\n",
+                "         more words\n",
+                "- service = api\n",
+                "Incidentally:\n",
+                "  Error closing connection after the\n",
+                "  request failed\n",
+                "    - peer = a\n",
+            )
+        );
+    }
+}
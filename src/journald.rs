@@ -0,0 +1,342 @@
+use {
+    crate::{
+        conversion::ResultContext,
+        sink::{
+            hash_bytes,
+            Event,
+            EventNode,
+            Sink,
+        },
+        value::Value,
+        Error,
+    },
+    std::{
+        fs::File,
+        io::Write,
+        os::{
+            fd::{
+                AsRawFd,
+                FromRawFd,
+                OwnedFd,
+            },
+            unix::net::UnixDatagram,
+        },
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+};
+
+/// Outputs events to systemd-journald using the native journal protocol.
+///
+/// The event message is sent as `MESSAGE`, the level as `PRIORITY`, and each
+/// attribute as a field with the upper-cased key (characters not allowed in journal
+/// field names are replaced with `_`).  Causes and incidental errors are sent with
+/// indexed field name prefixes, so the first cause's message is `CAUSE_0_MESSAGE`,
+/// the first incidental error of the first cause is at
+/// `CAUSE_0_INCIDENTAL_0_MESSAGE`, etc.  Source locations, if enabled, are sent as
+/// `CODE_FILE` and `CODE_LINE`, and backtraces, if captured, as `BACKTRACE`.
+///
+/// Attributes that would be sent as a journal protocol field (like `PRIORITY` or
+/// `SYSLOG_IDENTIFIER`) or that start with `CAUSE_`, `INCIDENTAL_` or `ATTR_` are
+/// prefixed with `ATTR_`.  Field names longer than the journal's 64 character limit
+/// are shortened and end with a hash of the full name.
+pub struct JournaldSink {
+    socket: UnixDatagram,
+    path: PathBuf,
+    identifier: Option<String>,
+}
+
+impl JournaldSink {
+    /// Create a sink sending to the standard journal socket at
+    /// `/run/systemd/journal/socket`.
+    pub fn new() -> Result<Self, Error> {
+        return Self::new_with_path("/run/systemd/journal/socket");
+    }
+
+    /// Create a sink sending to the journal protocol datagram socket at a custom path.
+    pub fn new_with_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(Error::from(format!("Journal socket at [{}] doesn't exist", path.display())));
+        }
+        return Ok(Self {
+            socket: UnixDatagram::unbound().context("Error creating journal client socket")?,
+            path: path.to_path_buf(),
+            identifier: None,
+        });
+    }
+
+    /// Send `SYSLOG_IDENTIFIER` with each event, which `journalctl` shows as the source
+    /// of the messages (defaults to the process name).
+    pub fn with_identifier(mut self, identifier: impl ToString) -> Self {
+        self.identifier = Some(identifier.to_string());
+        return self;
+    }
+
+    fn send(&self, payload: &[u8]) -> Result<(), Error> {
+        match self.socket.send_to(payload, &self.path) {
+            Ok(_) => return Ok(()),
+            Err(e) if e.raw_os_error() == Some(libc::EMSGSIZE) || e.raw_os_error() == Some(libc::ENOBUFS) => { },
+            Err(e) => return Err(e.into()),
+        }
+
+        // Too large for a datagram, send the payload via a sealed memfd instead
+        let memfd = unsafe {
+            libc::memfd_create(c"loga-journal".as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING)
+        };
+        if memfd < 0 {
+            return Err(std::io::Error::last_os_error()).context("Error creating memfd for large journal entry");
+        }
+        let memfd = unsafe {
+            OwnedFd::from_raw_fd(memfd)
+        };
+        let mut file = File::from(memfd);
+        file.write_all(payload).context("Error writing large journal entry to memfd")?;
+        if unsafe {
+            libc::fcntl(
+                file.as_raw_fd(),
+                libc::F_ADD_SEALS,
+                libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL,
+            )
+        } < 0 {
+            return Err(std::io::Error::last_os_error()).context("Error sealing large journal entry memfd");
+        }
+        send_fd(&self.socket, &self.path, file.as_raw_fd()).context("Error sending large journal entry memfd")?;
+        return Ok(());
+    }
+}
+
+impl Sink for JournaldSink {
+    fn write(&self, event: &Event) {
+        let mut payload = vec![];
+        write_field(&mut payload, "PRIORITY", event.level.syslog_severity().to_string().as_bytes());
+        if let Some(identifier) = &self.identifier {
+            write_field(&mut payload, "SYSLOG_IDENTIFIER", identifier.as_bytes());
+        }
+        write_node(&mut payload, "", &event.node);
+        _ = self.send(&payload);
+    }
+}
+
+fn write_node(payload: &mut Vec<u8>, prefix: &str, node: &EventNode) {
+    write_field(payload, &format!("{}MESSAGE", prefix), node.message.as_bytes());
+    if let Some(location) = node.location {
+        write_field(payload, &format!("{}CODE_FILE", prefix), location.file().as_bytes());
+        write_field(payload, &format!("{}CODE_LINE", prefix), location.line().to_string().as_bytes());
+    }
+    if let Some(backtrace) = &node.backtrace {
+        write_field(payload, &format!("{}BACKTRACE", prefix), backtrace.to_string().as_bytes());
+    }
+    for (k, v) in &node.attrs {
+        let key = format!("{}{}", prefix, field_name(k));
+        match v {
+            Value::Bytes(v) => write_field(payload, &key, v),
+            v => write_field(payload, &key, v.to_string().as_bytes()),
+        }
+    }
+    for (key, children) in [("CAUSE", &node.causes), ("INCIDENTAL", &node.incidental)] {
+        for (i, child) in children.iter().enumerate() {
+            write_node(payload, &format!("{}{}_{}_", prefix, key, i), child);
+        }
+    }
+}
+
+/// Convert an attribute key to a valid journal field name: upper case ASCII, digits
+/// and underscores, not starting with a digit or underscore.
+fn field_name(key: &str) -> String {
+    let mut out = String::new();
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_uppercase());
+        } else if !out.is_empty() {
+            out.push('_');
+        }
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert_str(0, "X_");
+    }
+    if is_reserved(&out) {
+        out.insert_str(0, "ATTR_");
+    }
+    return out;
+}
+
+/// Fields with special meaning to the journal, or used by this sink.
+const RESERVED: &[&str] = &[
+    "MESSAGE",
+    "MESSAGE_ID",
+    "PRIORITY",
+    "CODE_FILE",
+    "CODE_LINE",
+    "CODE_FUNC",
+    "BACKTRACE",
+    "ERRNO",
+    "INVOCATION_ID",
+    "USER_INVOCATION_ID",
+    "SYSLOG_FACILITY",
+    "SYSLOG_IDENTIFIER",
+    "SYSLOG_PID",
+    "SYSLOG_TIMESTAMP",
+    "SYSLOG_RAW",
+    "DOCUMENTATION",
+    "TID",
+    "UNIT",
+    "USER_UNIT",
+];
+
+fn is_reserved(name: &str) -> bool {
+    return RESERVED.contains(&name) || ["CAUSE_", "INCIDENTAL_", "ATTR_"].iter().any(|p| name.starts_with(p));
+}
+
+/// The journal ignores fields with names longer than this.
+const MAX_FIELD_NAME: usize = 64;
+
+/// Shorten a field name to the journal's limit, keeping it unique by replacing the
+/// end with a hash of the full name.
+fn limit_field_name(key: &str) -> String {
+    if key.len() <= MAX_FIELD_NAME {
+        return key.to_string();
+    }
+    let hash = format!("_{:016X}", hash_bytes(key.as_bytes()));
+    return format!("{}{}", &key[..MAX_FIELD_NAME - hash.len()], hash);
+}
+
+fn write_field(payload: &mut Vec<u8>, key: &str, value: &[u8]) {
+    payload.extend_from_slice(limit_field_name(key).as_bytes());
+    if value.contains(&b'\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value);
+    payload.push(b'\n');
+}
+
+/// Send an empty datagram carrying a file descriptor (`SCM_RIGHTS`) to the socket
+/// at `path`.
+fn send_fd(socket: &UnixDatagram, path: &Path, fd: libc::c_int) -> Result<(), std::io::Error> {
+    use std::os::unix::ffi::OsStrExt;
+
+    unsafe {
+        let mut addr: libc::sockaddr_un = std::mem::zeroed();
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let path_bytes = path.as_os_str().as_bytes();
+        if path_bytes.len() >= addr.sun_path.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Socket path too long"));
+        }
+        for (i, b) in path_bytes.iter().enumerate() {
+            addr.sun_path[i] = *b as libc::c_char;
+        }
+        let fd_size = std::mem::size_of::<libc::c_int>() as u32;
+        let mut control = vec![0u8; libc::CMSG_SPACE(fd_size) as usize];
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_name = &mut addr as *mut libc::sockaddr_un as *mut libc::c_void;
+        msg.msg_namelen = std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = control.len() as _;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fd_size) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, fd);
+        if libc::sendmsg(socket.as_raw_fd(), &msg, 0) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::JournaldSink,
+        crate::{
+            sink::{
+                Event,
+                EventNode,
+                Sink,
+            },
+            value::Value,
+            INFO,
+        },
+        std::{
+            backtrace::Backtrace,
+            os::unix::net::UnixDatagram,
+            sync::Arc,
+        },
+    };
+
+    /// Split a native journal protocol payload into fields.
+    fn parse(mut payload: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut out = vec![];
+        while !payload.is_empty() {
+            let end = payload.iter().position(|b| *b == b'\n' || *b == b'=').unwrap();
+            let key = String::from_utf8(payload[..end].to_vec()).unwrap();
+            let value;
+            if payload[end] == b'=' {
+                let value_end = end + 1 + payload[end + 1..].iter().position(|b| *b == b'\n').unwrap();
+                value = payload[end + 1 .. value_end].to_vec();
+                payload = &payload[value_end + 1..];
+            } else {
+                let len = u64::from_le_bytes(payload[end + 1 .. end + 9].try_into().unwrap()) as usize;
+                value = payload[end + 9 .. end + 9 + len].to_vec();
+                assert_eq!(payload[end + 9 + len], b'\n');
+                payload = &payload[end + 9 + len + 1..];
+            }
+            out.push((key, value));
+        }
+        return out;
+    }
+
+    #[test]
+    fn payload_framing() {
+        let dir = std::env::temp_dir().join(format!("loga-journald-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("socket");
+        _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        let sink = JournaldSink::new_with_path(&path).unwrap().with_identifier("test");
+        let mut deep = EventNode::new_test("Deepest", vec![("a_very_long_attribute_name_to_go_over_the_limit", Value::U64(1))], vec![]);
+        for _ in 0 .. 5 {
+            deep = EventNode::new_test("Layer", vec![], vec![deep]);
+        }
+        let backtrace = Arc::new(Backtrace::force_capture());
+        deep.backtrace = Some(backtrace.clone());
+        sink.write(&Event::new(INFO, EventNode::new_test("Multi\nline", vec![
+            ("priority", Value::Str("x".into())),
+            ("cause_0_message", Value::Str("y".into())),
+            ("attr_priority", Value::Str("z".into())),
+            ("data", Value::Bytes(b"a\nb=\x00".to_vec())),
+            ("user.name", Value::Str("me".into())),
+            ("backtrace", Value::Str("b".into()))
+        ], vec![deep])));
+        let mut buf = vec![0u8; 65536];
+        let len = server.recv(&mut buf).unwrap();
+        _ = std::fs::remove_dir_all(&dir);
+        let fields = parse(&buf[..len]);
+        let get = |k: &str| fields.iter().find(|(k1, _)| k1 == k).map(|(_, v)| v.as_slice());
+        assert_eq!(get("PRIORITY"), Some(b"6".as_slice()));
+        assert_eq!(fields.iter().filter(|(k, _)| k == "PRIORITY").count(), 1);
+        assert_eq!(get("SYSLOG_IDENTIFIER"), Some(b"test".as_slice()));
+        assert_eq!(get("MESSAGE"), Some(b"Multi\nline".as_slice()));
+        assert_eq!(get("ATTR_PRIORITY"), Some(b"x".as_slice()));
+        assert_eq!(get("ATTR_CAUSE_0_MESSAGE"), Some(b"y".as_slice()));
+        assert_eq!(get("ATTR_ATTR_PRIORITY"), Some(b"z".as_slice()));
+        assert_eq!(get("DATA"), Some(b"a\nb=\x00".as_slice()));
+        assert_eq!(get("USER_NAME"), Some(b"me".as_slice()));
+        assert_eq!(get("CAUSE_0_MESSAGE"), Some(b"Layer".as_slice()));
+        assert_eq!(get("CAUSE_0_BACKTRACE"), Some(backtrace.to_string().as_bytes()));
+        assert_eq!(get("ATTR_BACKTRACE"), Some(b"b".as_slice()));
+        assert_eq!(get("CAUSE_0_CAUSE_0_CAUSE_0_CAUSE_0_CAUSE_0_CAUSE_0_MESSAGE"), Some(b"Deepest".as_slice()));
+        let (long_key, _) = fields.iter().find(|(_, v)| v == b"1").unwrap();
+        assert_eq!(long_key.len(), 64);
+        assert!(long_key.starts_with("CAUSE_0_CAUSE_0_CAUSE_0_CAUSE_0_CAUSE_0_"));
+        for (k, _) in &fields {
+            assert!(k.len() <= 64);
+            assert!(k.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'));
+        }
+    }
+}
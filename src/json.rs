@@ -0,0 +1,185 @@
+use crate::{
+    format::Format,
+    sink::{
+        Event,
+        EventNode,
+    },
+    value::Value,
+};
+
+/// Renders events as JSON Lines, one object per event.  The object has `ts`
+/// (RFC 3339), `level`, and the fields of the error tree: `msg`, `msg_template`
+/// (if the message was formatted by a macro like `info!`), `at` (the source
+/// location, if enabled), `backtrace` (if captured), `attrs`, and (when non-empty) `causes` and `incidental`,
+/// each a list of objects with the same tree fields.
+///
+/// Attribute values are output as the corresponding JSON types, except bytes which
+/// are output as hex strings and non-finite floats which are output as strings.
+#[derive(Default)]
+pub struct JsonFormat;
+
+impl JsonFormat {
+    pub fn new() -> Self {
+        return Self;
+    }
+}
+
+impl Format for JsonFormat {
+    fn render(&self, event: &Event, out: &mut String) {
+        out.push_str("{\"ts\":");
+        write_str(out, &event.time.to_rfc3339());
+        out.push_str(",\"level\":");
+        write_str(out, event.level.name());
+        out.push(',');
+        write_node_fields(out, &event.node);
+        out.push_str("}\n");
+    }
+}
+
+fn write_node_fields(out: &mut String, node: &EventNode) {
+    out.push_str("\"msg\":");
+    write_str(out, &node.message);
+    if let Some(template) = &node.template {
+        out.push_str(",\"msg_template\":");
+        write_str(out, template);
+    }
+    if let Some(location) = node.location {
+        out.push_str(",\"at\":");
+        write_str(out, &format!("{}:{}", location.file(), location.line()));
+    }
+    if let Some(backtrace) = &node.backtrace {
+        out.push_str(",\"backtrace\":");
+        write_str(out, &backtrace.to_string());
+    }
+    out.push_str(",\"attrs\":{");
+    for (i, (k, v)) in node.attrs.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_str(out, k);
+        out.push(':');
+        write_value(out, v);
+    }
+    out.push('}');
+    for (key, children) in [("causes", &node.causes), ("incidental", &node.incidental)] {
+        if children.is_empty() {
+            continue;
+        }
+        out.push_str(",\"");
+        out.push_str(key);
+        out.push_str("\":[");
+        for (i, child) in children.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push('{');
+            write_node_fields(out, child);
+            out.push('}');
+        }
+        out.push(']');
+    }
+}
+
+pub(crate) fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Str(v) => write_str(out, v),
+        Value::I64(v) => out.push_str(&v.to_string()),
+        Value::U64(v) => out.push_str(&v.to_string()),
+        Value::F64(v) => {
+            if v.is_finite() {
+                out.push_str(&v.to_string());
+            } else {
+                // Not representable as a JSON number
+                write_str(out, &v.to_string());
+            }
+        },
+        Value::Bool(v) => out.push_str(if *v {
+            "true"
+        } else {
+            "false"
+        }),
+        Value::Bytes(_) => write_str(out, &value.to_string()),
+        Value::List(v) => {
+            out.push('[');
+            for (i, e) in v.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, e);
+            }
+            out.push(']');
+        },
+        Value::Map(v) => {
+            out.push('{');
+            for (i, (k, e)) in v.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_str(out, k);
+                out.push(':');
+                write_value(out, e);
+            }
+            out.push('}');
+        },
+    }
+}
+
+/// Write a string as a quoted, escaped JSON string.
+pub(crate) fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::JsonFormat,
+        crate::{
+            format::Format,
+            sink::{
+                Event,
+                EventNode,
+            },
+            value::Value,
+            WARN,
+        },
+        chrono::DateTime,
+    };
+
+    #[test]
+    fn escaping() {
+        let mut out = String::new();
+        JsonFormat::new().render(&Event {
+            level: WARN,
+            time: DateTime::parse_from_rfc3339("2024-01-02T03:04:05+00:00").unwrap(),
+            node: EventNode::new_test("Say \"hi\"\n\\ \u{1} ü", vec![
+                ("k\"ey", Value::Str("tab\there".into())),
+                ("nan", Value::F64(f64::NAN)),
+                ("bytes", Value::Bytes(vec![0, 255])),
+                ("list", Value::List(vec![Value::Null, Value::Bool(true), Value::I64(-1)])),
+                ("map", Value::Map(vec![("a\\".into(), Value::U64(1))]))
+            ], vec![EventNode::new_test("Inner\r", vec![], vec![])]),
+        }, &mut out);
+        assert_eq!(
+            out,
+            concat!(
+                r#"{"ts":"2024-01-02T03:04:05+00:00","level":"WARN","msg":"Say \"hi\"\n\\ \u0001 ü","#,
+                r#""attrs":{"k\"ey":"tab\there","nan":"NaN","bytes":"00ff","list":[null,true,-1],"map":{"a\\":1}},"#,
+                r#""causes":[{"msg":"Inner\r","attrs":{}}]}"#,
+                "\n"
+            )
+        );
+    }
+}
@@ -1,11 +1,31 @@
 pub mod entry;
 pub mod common;
+mod macros;
+pub mod value;
 pub mod types;
 pub mod conversion;
+pub mod sink;
+pub mod format;
+pub mod json;
+pub mod logfmt;
+pub mod file;
+pub mod background;
+pub mod capture;
+mod rate_limit;
+pub mod std_error;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(target_os = "linux")]
+pub mod journald;
+pub mod syslog;
 
+#[doc(hidden)]
+pub use macros::__loga_check_template;
 pub use types::{
     Error,
     Log,
+    RootBuilder,
+    Walk,
 };
 pub use entry::{
     err,
@@ -15,19 +35,58 @@ pub use entry::{
     fatal,
 };
 pub use common::{
+    Attrs,
     DebugDisplay,
     Level,
     DEBUG,
     INFO,
     WARN,
     ERR,
+    FATAL,
+};
+pub use value::{
+    ToValue,
+    Value,
 };
 pub use conversion::{
     ErrContext,
     ResultContext,
 };
+pub use sink::{
+    Event,
+    EventNode,
+    Sink,
+    StderrSink,
+};
+pub use format::{
+    Format,
+    TextFormat,
+};
+pub use json::JsonFormat;
+pub use logfmt::LogfmtFormat;
+pub use background::{
+    BackgroundSink,
+    FlushGuard,
+    OverflowPolicy,
+};
+pub use std_error::StdError;
+pub use capture::{
+    CaptureQuery,
+    Captured,
+};
+pub use file::{
+    FileSink,
+    Rotation,
+};
+#[cfg(target_os = "linux")]
+pub use journald::JournaldSink;
+pub use syslog::{
+    SyslogFacility,
+    SyslogSink,
+};
 
 /// Re-exported dependencies used in interfaces, etc.
 pub mod republish {
+    pub use chrono;
     pub use console;
 }
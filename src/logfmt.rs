@@ -0,0 +1,167 @@
+use crate::{
+    format::Format,
+    sink::{
+        Event,
+        EventNode,
+    },
+};
+
+/// Renders each event as a single logfmt line, ex: `ts=... level=INFO msg="Example
+/// message" system=main`.
+///
+/// Attributes of the top level error are output with their plain keys.  Causes and
+/// incidental errors are output with indexed key prefixes, so the first cause's
+/// message is `cause.0.msg`, the first incidental error of the first cause is at
+/// `cause.0.incidental.0.msg`, etc.
+///
+/// Attribute keys that could be confused with these keys (like `level`, `msg`,
+/// `cause.0.msg`, or keys with numeric segments) are prefixed with `attr.`, so
+/// `ea!(level = 3)` is output as `attr.level=3`.
+#[derive(Default)]
+pub struct LogfmtFormat;
+
+impl LogfmtFormat {
+    pub fn new() -> Self {
+        return Self;
+    }
+}
+
+impl Format for LogfmtFormat {
+    fn render(&self, event: &Event, out: &mut String) {
+        out.push_str("ts=");
+        out.push_str(&event.time.to_rfc3339());
+        write_pair(out, "", "level", event.level.name());
+        write_node(out, "", &event.node);
+        out.push('\n');
+    }
+}
+
+fn write_node(out: &mut String, prefix: &str, node: &EventNode) {
+    write_pair(out, prefix, "msg", &node.message);
+    if let Some(template) = &node.template {
+        write_pair(out, prefix, "msg_template", template);
+    }
+    if let Some(location) = node.location {
+        write_pair(out, prefix, "at", &format!("{}:{}", location.file(), location.line()));
+    }
+    if let Some(backtrace) = &node.backtrace {
+        write_pair(out, prefix, "backtrace", &backtrace.to_string());
+    }
+    for (k, v) in &node.attrs {
+        if is_reserved(k) {
+            write_pair(out, prefix, &format!("attr.{}", k), &v.to_string());
+        } else {
+            write_pair(out, prefix, k, &v.to_string());
+        }
+    }
+    for (key, children) in [("cause", &node.causes), ("incidental", &node.incidental)] {
+        for (i, child) in children.iter().enumerate() {
+            write_node(out, &format!("{}{}.{}.", prefix, key, i), child);
+        }
+    }
+}
+
+/// Whether an attribute key could be confused with the keys for event fields or
+/// nested errors and needs to be escaped with the `attr.` prefix.
+fn is_reserved(key: &str) -> bool {
+    if ["ts", "level", "msg", "msg_template", "at", "backtrace"].contains(&key) {
+        return true;
+    }
+    if let Some("cause" | "incidental" | "attr") = key.split('.').next() {
+        return true;
+    }
+    if key.split('.').any(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())) {
+        return true;
+    }
+    return false;
+}
+
+fn write_pair(out: &mut String, prefix: &str, key: &str, value: &str) {
+    out.push(' ');
+    out.push_str(prefix);
+    for c in key.chars() {
+        if c == '=' || c == '"' || c.is_whitespace() || c.is_control() {
+            out.push('_');
+        } else {
+            out.push(c);
+        }
+    }
+    out.push('=');
+    if !value.is_empty() && !value.chars().any(|c| c == '=' || c == '"' || c == '\\' || c.is_whitespace() || c.is_control()) {
+        out.push_str(value);
+        return;
+    }
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::LogfmtFormat,
+        crate::{
+            format::Format,
+            sink::{
+                Event,
+                EventNode,
+            },
+            value::Value,
+            INFO,
+        },
+        chrono::DateTime,
+    };
+
+    fn render(node: EventNode) -> String {
+        let mut out = String::new();
+        LogfmtFormat::new().render(&Event {
+            level: INFO,
+            time: DateTime::parse_from_rfc3339("2024-01-02T03:04:05+00:00").unwrap(),
+            node: node,
+        }, &mut out);
+        return out;
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            render(EventNode::new_test("Say \"hi\"\n", vec![
+                ("plain", Value::Str("abc".into())),
+                ("empty", Value::Str("".into())),
+                ("spaced key", Value::Str("a=b \\ c\t\u{1}".into()))
+            ], vec![])),
+            concat!(
+                r#"ts=2024-01-02T03:04:05+00:00 level=INFO msg="Say \"hi\"\n" plain=abc empty="" "#,
+                r#"spaced_key="a=b \\ c\t\u0001""#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn reserved_keys() {
+        assert_eq!(
+            render(EventNode::new_test("Outer", vec![
+                ("level", Value::U64(3)),
+                ("cause.0.msg", Value::Str("x".into())),
+                ("attr.level", Value::U64(4)),
+                ("retry.1", Value::Bool(true)),
+                ("user.id", Value::U64(5))
+            ], vec![EventNode::new_test("Inner", vec![("msg", Value::Str("y".into()))], vec![])])),
+            concat!(
+                "ts=2024-01-02T03:04:05+00:00 level=INFO msg=Outer attr.level=3 attr.cause.0.msg=x ",
+                "attr.attr.level=4 attr.retry.1=true user.id=5 cause.0.msg=Inner cause.0.attr.msg=y\n"
+            )
+        );
+    }
+}
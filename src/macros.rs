@@ -0,0 +1,312 @@
+/// Log a formatted message at `DEBUG` level.  See `info!`.
+#[macro_export]
+macro_rules! debug{
+    ($log: expr, $($t: tt) *) => {
+        $crate::__loga_log!($log, $crate::DEBUG, $($t) *)
+    };
+}
+
+/// Log a formatted message at `INFO` level.  Use like `info!(log, "Fetched {}
+/// rows from {table}", n; ea!(db = name))` - the format string and arguments are
+/// the same as `format!`, and the optional attributes after `;` are the same as
+/// `Log::log_with`.
+///
+/// Neither the arguments nor the attributes are evaluated if the level is disabled
+/// for the `Log`.
+///
+/// The format string is kept with the event (see `EventNode::template`) and named
+/// arguments (`name = value`) are also added as attributes.  Variables can't be
+/// captured inline like `format!("{name}")` since they wouldn't be recorded, this
+/// is a compile error - write `{name}` with `name = name` instead.
+#[macro_export]
+macro_rules! info{
+    ($log: expr, $($t: tt) *) => {
+        $crate::__loga_log!($log, $crate::INFO, $($t) *)
+    };
+}
+
+/// Log a formatted message at `WARN` level.  See `info!`.
+#[macro_export]
+macro_rules! warn{
+    ($log: expr, $($t: tt) *) => {
+        $crate::__loga_log!($log, $crate::WARN, $($t) *)
+    };
+}
+
+/// Log a formatted message at `ERR` level.  See `info!`.
+#[macro_export]
+macro_rules! error{
+    ($log: expr, $($t: tt) *) => {
+        $crate::__loga_log!($log, $crate::ERR, $($t) *)
+    };
+}
+
+/// Create an error with a formatted message, like `err_with`. Use like
+/// `err!("Failed to open {}", path; ea!(user = name))`, or `err!(log, "Failed to
+/// open {}", path)` to include the attributes of a `Log` like `Log::err_with`. The
+/// attributes after `;` are optional.
+#[macro_export]
+macro_rules! err{
+    ($fmt: literal $($t: tt) *) => {
+        $crate::__loga_split!(err[] $fmt $($t) *)
+    };
+    ($log: expr, $fmt: literal $($t: tt) *) => {
+        $crate::__loga_split!(log[$log] $fmt $($t) *)
+    };
+}
+
+/// Add a layer of context with a formatted message to an error, like
+/// `ErrContext::context_with`. Use like `context!(e, "Failed to open {}", path;
+/// ea!(user = name))`, or `context!(e, log, "Failed to open {}", path)` to include
+/// the attributes of a `Log` like `ErrContext::stack_context_with`.  The attributes
+/// after `;` are optional.
+#[macro_export]
+macro_rules! context{
+    ($e: expr, $fmt: literal $($t: tt) *) => {
+        $crate::__loga_split!(context[$e] $fmt $($t) *)
+    };
+    ($e: expr, $log: expr, $fmt: literal $($t: tt) *) => {
+        $crate::__loga_split!(stack_context[$e, $log] $fmt $($t) *)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __loga_log{
+    ($log: expr, $level: expr, $($t: tt) *) => {
+        {
+            let log: &$crate::Log = &$log;
+            let level: $crate::Level = $level;
+            if log.enabled(level) {
+                log.log_err(level, $crate::__loga_split!(event[log] $($t) *));
+            }
+        }
+    };
+}
+
+/// Parse the format string and arguments, then the optional attributes after `;`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __loga_split{
+    ($mode: ident[$($fixed: tt) *] $fmt: literal $($args: tt) *) => {
+        $crate::__loga_args!($mode[$($fixed) *] $fmt[][] $($args) *)
+    };
+}
+
+/// Separate positional and named format arguments, one argument at a time.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __loga_args{
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *] $(,)?) => {
+        $crate::__loga_build!($mode[$($fixed) *] $fmt($crate::ea!())[$($pos) *][$($named) *])
+    };
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *] $(,)?; $attrs: expr) => {
+        $crate::__loga_build!($mode[$($fixed) *] $fmt($attrs)[$($pos) *][$($named) *])
+    };
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *], $n: ident = $v: expr, $($rest: tt) *) => {
+        $crate::__loga_args!($mode[$($fixed) *] $fmt[$($pos) *][$($named) *($n, $v)], $($rest) *)
+    };
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *], $n: ident = $v: expr; $attrs: expr) => {
+        $crate::__loga_build!($mode[$($fixed) *] $fmt($attrs)[$($pos) *][$($named) *($n, $v)])
+    };
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *], $n: ident = $v: expr) => {
+        $crate::__loga_build!($mode[$($fixed) *] $fmt($crate::ea!())[$($pos) *][$($named) *($n, $v)])
+    };
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *], $v: expr, $($rest: tt) *) => {
+        $crate::__loga_args!($mode[$($fixed) *] $fmt[$($pos) *($v)][$($named) *], $($rest) *)
+    };
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *], $v: expr; $attrs: expr) => {
+        $crate::__loga_build!($mode[$($fixed) *] $fmt($attrs)[$($pos) *($v)][$($named) *])
+    };
+    ($mode: ident[$($fixed: tt) *] $fmt: literal[$($pos: tt) *][$($named: tt) *], $v: expr) => {
+        $crate::__loga_build!($mode[$($fixed) *] $fmt($crate::ea!())[$($pos) *($v)][$($named) *])
+    };
+}
+
+/// Evaluate named arguments once, using them both for formatting and as attributes,
+/// then build the error.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __loga_build{
+    ($mode: ident[$($fixed: tt) *] $fmt: literal ($attrs: expr)[$(($pos: expr)) *][$(($n: ident, $v: expr)) *]) => {
+        match ($(&$v,)*) {
+            ($($n,)*) => {
+                const _: () = $crate::__loga_check_template($fmt, &[$(stringify!($n)), *]);
+                let message = ::std::format!($fmt $(, $pos) * $(, $n = $n) *);
+                let extra: &dyn Fn(&mut $crate::Attrs) = &$attrs;
+                let attrs = |attrs: &mut $crate::Attrs| {
+                    #[allow(unused_imports)]
+                    use $crate::value::{
+                        ViaDisplay as _,
+                        ViaToValue as _,
+                    };
+                    $(attrs.insert(stringify!($n), (&$crate::value::ValueWrap($n)).loga_value());) *
+                    extra(attrs);
+                };
+                $crate::__loga_finish!($mode[$($fixed) *] message attrs).__with_template($fmt)
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __loga_finish{
+    (log[$log: expr] $message: ident $attrs: ident) => {
+        $log.err_with($message, $attrs)
+    };
+    (event[$log: expr] $message: ident $attrs: ident) => {
+        $log.__event_with($message, $attrs)
+    };
+    (err[] $message: ident $attrs: ident) => {
+        $crate::err_with($message, $attrs)
+    };
+    (context[$e: expr] $message: ident $attrs: ident) => {
+        $crate::ErrContext::context_with($e, $message, $attrs)
+    };
+    (stack_context[$e: expr, $log: expr] $message: ident $attrs: ident) => {
+        $crate::ErrContext::stack_context_with($e, &$log, $message, $attrs)
+    };
+}
+
+/// Fail (at compile time, when used in a constant) if the format string refers to
+/// an argument by name that isn't one of the named arguments, that is if a variable
+/// is captured inline.
+#[doc(hidden)]
+pub const fn __loga_check_template(template: &str, named: &[&str]) {
+    let t = template.as_bytes();
+    let mut i = 0;
+    while i < t.len() {
+        if t[i] != b'{' {
+            i += 1;
+            continue;
+        }
+        if i + 1 < t.len() && t[i + 1] == b'{' {
+            i += 2;
+            continue;
+        }
+
+        // Check the argument, and any `name$` width or precision arguments in the spec
+        let mut start = i + 1;
+        let mut at = start;
+        while at < t.len() && t[at] != b'}' {
+            if t[at] == b':' || t[at] == b'$' {
+                check_template_arg(t, start, at, named);
+                start = at + 1;
+            } else if !(t[at].is_ascii_alphanumeric() || t[at] == b'_') {
+                start = at + 1;
+            }
+            at += 1;
+        }
+        if start == i + 1 {
+            check_template_arg(t, start, at, named);
+        }
+        i = at + 1;
+    }
+}
+
+const fn check_template_arg(t: &[u8], start: usize, end: usize, named: &[&str]) {
+    if start >= end || !(t[start].is_ascii_alphabetic() || t[start] == b'_') {
+        return;
+    }
+    let mut i = 0;
+    while i < named.len() {
+        let n = named[i].as_bytes();
+        if n.len() == end - start {
+            let mut j = 0;
+            while j < n.len() && n[j] == t[start + j] {
+                j += 1;
+            }
+            if j == n.len() {
+                return;
+            }
+        }
+        i += 1;
+    }
+    panic!("Variables captured inline in the format string aren't recorded as attributes, pass them as named arguments like `name = name`");
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            ea,
+            Log,
+            Value,
+            INFO,
+        },
+        std::cell::Cell,
+    };
+
+    struct S {
+        a: Cell<i64>,
+    }
+
+    #[test]
+    fn forms() {
+        let (log, captured) = Log::new_capture(INFO);
+        let name = "x";
+        crate::info!(log, "Plain");
+        crate::info!(log, "Positional {} {}", 1, name,);
+        crate::info!(log, "Named {n} {name}", n = 2, name = name; ea!(extra = true));
+        crate::info!(log, "Mixed {} {n}", 3, n = 4,; ea!(extra = true));
+        crate::info!(log, "Attrs only"; ea!(extra = true));
+        let events = captured.events();
+        let messages = events.iter().map(|e| e.node.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, ["Plain", "Positional 1 x", "Named 2 x", "Mixed 3 4", "Attrs only"]);
+        assert_eq!(events[2].node.attr("n"), Some(&Value::I64(2)));
+        assert_eq!(events[2].node.attr("name"), Some(&Value::Str("x".into())));
+        assert_eq!(events[2].node.attr("extra"), Some(&Value::Bool(true)));
+        assert_eq!(events[2].node.template.as_deref(), Some("Named {n} {name}"));
+        assert_eq!(events[3].node.attrs.len(), 2);
+        let e = crate::err!(log, "Error {} {n}", 1, n = 2; ea!(extra = true));
+        assert_eq!(e.message(), "Error 1 2");
+        assert_eq!(e.find_attr("n"), Some(Value::I64(2)));
+        let e = crate::context!(crate::err!("Inner {}", 1), "Outer {n}", n = 2);
+        assert_eq!(e.message(), "Outer 2");
+        assert_eq!(e.causes()[0].message(), "Inner 1");
+    }
+
+    #[test]
+    fn check_template() {
+        use super::__loga_check_template as check;
+
+        check("Plain {{name}} {} {0} {:?} {:>10} {:x} {:.*}", &[]);
+        check("{name} {name:?} {name:>width$} {:.prec$}", &["name", "width", "prec"]);
+        for template in ["{name}", "{name:?}", "{:width$}", "{0:.prec$}", "{{}} {name}"] {
+            assert!(std::panic::catch_unwind(|| check(template, &["other"])).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn many_arguments() {
+        let (log, captured) = Log::new_capture(INFO);
+        let s = S { a: Cell::new(1) };
+        crate::info!(
+            log,
+            "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get(),
+            s.a.get();
+            ea!(extra = true)
+        );
+        assert_eq!(captured.query().attr("extra", true).one().node.message, ["1"; 20].join(" "));
+    }
+}
@@ -0,0 +1,223 @@
+use {
+    crate::{
+        sink::EventNode,
+        types::RootConfig,
+        value::Value,
+        Level,
+    },
+    std::{
+        collections::{
+            hash_map::Entry,
+            HashMap,
+        },
+        sync::{
+            Arc,
+            Mutex,
+            Weak,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+};
+
+/// All live limiters, so pending summaries can be output before exiting in `fatal`.
+static LIMITERS: Mutex<Vec<Weak<RateLimiter>>> = Mutex::new(vec![]);
+
+struct Window {
+    start: Instant,
+    count: usize,
+    suppressed: usize,
+    level: Level,
+    message: String,
+}
+
+struct Windows {
+    windows: HashMap<u64, Window>,
+    /// The number of windows after the last sweep for expired windows.
+    swept_len: usize,
+}
+
+/// Suppresses events with the same fingerprint (see `EventNode::fingerprint`)
+/// beyond a maximum number per interval.
+pub(crate) struct RateLimiter {
+    max: usize,
+    interval: Duration,
+    config: Arc<RootConfig>,
+    windows: Mutex<Windows>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(max: usize, interval: Duration, config: Arc<RootConfig>) -> Arc<Self> {
+        let limiter = Arc::new(Self {
+            max: max,
+            interval: interval,
+            config: config,
+            windows: Mutex::new(Windows {
+                windows: HashMap::new(),
+                swept_len: 0,
+            }),
+        });
+        let mut limiters = LIMITERS.lock().unwrap();
+        limiters.retain(|l| l.strong_count() > 0);
+        limiters.push(Arc::downgrade(&limiter));
+        return limiter;
+    }
+
+    /// Record an event, returning whether it should be output.  If the event's previous
+    /// window ended with suppressed events, the summary is output first.
+    pub(crate) fn check(&self, level: Level, node: &EventNode) -> bool {
+        let now = Instant::now();
+        let mut summaries = vec![];
+        let output;
+        {
+            let mut windows = self.windows.lock().unwrap();
+
+            // Occasionally drop expired windows so the map doesn't grow without bound.
+            // Sweeping only when the map doubles keeps the cost per event constant.
+            if windows.windows.len() >= (windows.swept_len * 2).max(64) {
+                windows.windows.retain(|_, window| {
+                    if now.duration_since(window.start) < self.interval {
+                        return true;
+                    }
+                    if window.suppressed > 0 {
+                        summaries.push((window.level, summary(window)));
+                    }
+                    return false;
+                });
+                windows.swept_len = windows.windows.len();
+            }
+            let window = match windows.windows.entry(node.fingerprint()) {
+                Entry::Occupied(e) => {
+                    let window = e.into_mut();
+                    if now.duration_since(window.start) >= self.interval {
+                        if window.suppressed > 0 {
+                            summaries.push((window.level, summary(window)));
+                        }
+                        window.start = now;
+                        window.count = 0;
+                        window.suppressed = 0;
+                        window.level = level;
+                        window.message = node.message.clone();
+                    }
+                    window
+                },
+                Entry::Vacant(e) => e.insert(Window {
+                    start: now,
+                    count: 0,
+                    suppressed: 0,
+                    level: level,
+                    message: node.message.clone(),
+                }),
+            };
+            window.count += 1;
+            output = window.count <= self.max;
+            if !output {
+                window.suppressed += 1;
+            }
+        }
+        for (level, summary) in summaries {
+            self.config.write(level, summary);
+        }
+        return output;
+    }
+
+    /// Output summaries for all windows with suppressed events.
+    fn flush(&self) {
+        let mut summaries = vec![];
+        for window in self.windows.lock().unwrap().windows.values_mut() {
+            if window.suppressed > 0 {
+                summaries.push((window.level, summary(window)));
+                window.suppressed = 0;
+            }
+        }
+        for (level, summary) in summaries {
+            self.config.write(level, summary);
+        }
+    }
+}
+
+impl Drop for RateLimiter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Output the pending summaries of all limiters.
+pub(crate) fn flush_all() {
+    let limiters = LIMITERS.lock().unwrap().iter().filter_map(|l| l.upgrade()).collect::<Vec<_>>();
+    for limiter in limiters {
+        limiter.flush();
+    }
+}
+
+fn summary(window: &Window) -> EventNode {
+    return EventNode {
+        message: format!("Previous message repeated {} times", window.suppressed),
+        template: Some("Previous message repeated {count} times".into()),
+        location: None,
+        backtrace: None,
+        attrs: vec![
+            ("count".into(), Value::U64(window.suppressed as u64)),
+            ("message".into(), Value::Str(window.message.clone()))
+        ],
+        causes: vec![],
+        incidental: vec![],
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            info,
+            Log,
+            INFO,
+        },
+        std::time::Duration,
+    };
+
+    #[test]
+    fn suppress_and_summarize() {
+        let (log, captured) = Log::new_capture(INFO);
+        let limited = log.fork_with_rate_limit(2, Duration::from_millis(100), |_| { });
+        for i in 0 .. 5 {
+            info!(limited, "Fetched {n} rows", n = i);
+        }
+        limited.log(INFO, "Other");
+        log.log(INFO, "Unlimited");
+        log.log(INFO, "Unlimited");
+        log.log(INFO, "Unlimited");
+        assert_eq!(captured.query().message("Fetched").count(), 2);
+        assert_eq!(captured.query().message("Other").count(), 1);
+        assert_eq!(captured.query().message("Unlimited").count(), 3);
+        captured.query().message("repeated").assert_none();
+
+        // The summary is output when the key is next seen after the interval
+        std::thread::sleep(Duration::from_millis(150));
+        info!(limited, "Fetched {n} rows", n = 5);
+        let messages = captured.events().into_iter().map(|e| e.node.message).collect::<Vec<_>>();
+        assert_eq!(messages[messages.len() - 2 ..], ["Previous message repeated 3 times", "Fetched 5 rows"]);
+        assert_eq!(captured.query().message("repeated").attr("count", 3).attr("message", "Fetched 0 rows").count(), 1);
+
+        // Summaries use the first message of the window they summarize
+        for i in 6 .. 9 {
+            info!(limited, "Fetched {n} rows", n = i);
+        }
+        drop(limited);
+        assert_eq!(captured.query().message("repeated").attr("count", 2).attr("message", "Fetched 5 rows").count(), 1);
+    }
+
+    #[test]
+    fn summarize_on_drop() {
+        let (log, captured) = Log::new_capture(INFO);
+        let limited = log.fork_with_rate_limit(1, Duration::from_secs(3600), |_| { });
+        for _ in 0 .. 3 {
+            limited.log(INFO, "Retrying");
+        }
+        captured.query().message("repeated").assert_none();
+        drop(limited);
+        assert_eq!(captured.query().message("repeated").attr("count", 2).attr("message", "Retrying").count(), 1);
+    }
+}
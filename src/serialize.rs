@@ -0,0 +1,188 @@
+use {
+    crate::{
+        common::Attrs,
+        sink::EventNode,
+        types::{
+            Error,
+            Error_,
+        },
+        value::Value,
+    },
+    serde::{
+        Deserialize,
+        Deserializer,
+        Serialize,
+        Serializer,
+    },
+    std::{
+        borrow::Cow,
+        collections::HashSet,
+    },
+};
+
+/// The serialized form of an error.  Attributes from `Log` contexts are flattened
+/// into the attributes of each layer, the same as when the error is output, so the
+/// deserialized error renders identically.  Source locations, backtraces, and
+/// original foreign errors aren't serialized.
+#[derive(Serialize, Deserialize)]
+struct SerialError {
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    template: Option<Cow<'static, str>>,
+    #[serde(default)]
+    attrs: Vec<(Cow<'static, str>, Value)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    causes: Vec<SerialError>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    incidental: Vec<SerialError>,
+}
+
+impl SerialError {
+    fn from_node(node: EventNode) -> Self {
+        return Self {
+            message: node.message,
+            template: node.template,
+            attrs: node.attrs,
+            causes: node.causes.into_iter().map(SerialError::from_node).collect(),
+            incidental: node.incidental.into_iter().map(SerialError::from_node).collect(),
+        };
+    }
+
+    fn into_error(self) -> Error {
+        let mut attrs = Attrs::new();
+        for (k, v) in self.attrs {
+            attrs.insert(k, v);
+        }
+        return Error(Box::new(Error_ {
+            message: self.message,
+            attrs: attrs,
+            context: vec![],
+            causes: self.causes.into_iter().map(SerialError::into_error).collect(),
+            incidental: self.incidental.into_iter().map(SerialError::into_error).collect(),
+            template: self.template,
+            location: None,
+            backtrace: None,
+            original: None,
+        }));
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return SerialError::from_node(self.build_event_node(&HashSet::new())).serialize(serializer);
+    }
+}
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        return Ok(SerialError::deserialize(deserializer)?.into_error());
+    }
+}
+
+/// Serialize non-finite floats as strings in human readable formats (JSON can't
+/// represent them as numbers), the same as `JsonFormat`.  Binary formats use plain
+/// floats.
+pub(crate) mod f64_value {
+    use serde::{
+        de::{
+            Error,
+            Visitor,
+        },
+        Deserializer,
+        Serializer,
+    };
+
+    pub(crate) fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_finite() || !serializer.is_human_readable() {
+            return serializer.serialize_f64(*value);
+        } else {
+            return serializer.serialize_str(&value.to_string());
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        struct F64Visitor;
+
+        impl<'de> Visitor<'de> for F64Visitor {
+            type Value = f64;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                return formatter.write_str("a number or a string like NaN or inf");
+            }
+
+            fn visit_f64<E: Error>(self, v: f64) -> Result<f64, E> {
+                return Ok(v);
+            }
+
+            fn visit_i64<E: Error>(self, v: i64) -> Result<f64, E> {
+                return Ok(v as f64);
+            }
+
+            fn visit_u64<E: Error>(self, v: u64) -> Result<f64, E> {
+                return Ok(v as f64);
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<f64, E> {
+                return v.parse::<f64>().map_err(E::custom);
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            return deserializer.deserialize_any(F64Visitor);
+        } else {
+            return deserializer.deserialize_f64(F64Visitor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ea,
+        err,
+        err_with,
+        Error,
+        Log,
+        Value,
+        INFO,
+    };
+
+    #[test]
+    fn round_trip() {
+        let (log, _captured) = Log::new_capture(INFO);
+        let log = log.fork(ea!(service = "api"));
+        let e = err_with("Connection lost", ea!(
+            port = 8080u64,
+            offset = -1,
+            ratio = 0.5,
+            nan = f64::NAN,
+            inf = f64::NEG_INFINITY,
+            ok = false,
+            data = Value::Bytes(vec![1, 2]),
+            tags = vec!["a", "b"],
+            http.status = 500
+        ))
+            .stack_context(&log, "Error running query")
+            .also(err!("Error closing {what}", what = "socket"));
+        let json = serde_json::to_string(&e).unwrap();
+        let e2: Error = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&e2).unwrap(), json);
+        assert_eq!(e2.message(), "Error running query");
+        assert_eq!(e2.find_attr("service"), Some(Value::Str("api".into())));
+        let cause = &e2.causes()[0];
+        assert_eq!(cause.find_attr("port"), Some(Value::U64(8080)));
+        assert_eq!(cause.find_attr("offset"), Some(Value::I64(-1)));
+        assert_eq!(cause.find_attr("ratio"), Some(Value::F64(0.5)));
+        assert!(cause.find_attr("nan").unwrap().as_f64().unwrap().is_nan());
+        assert_eq!(cause.find_attr("inf"), Some(Value::F64(f64::NEG_INFINITY)));
+        assert_eq!(cause.find_attr("ok"), Some(Value::Bool(false)));
+        assert_eq!(cause.find_attr("data"), Some(Value::Bytes(vec![1, 2])));
+        assert_eq!(
+            cause.find_attr("tags"),
+            Some(Value::List(vec![Value::Str("a".into()), Value::Str("b".into())]))
+        );
+        assert_eq!(cause.find_attr("http"), Some(Value::Map(vec![("status".into(), Value::I64(500))])));
+        assert_eq!(e2.incidental()[0].template(), Some("Error closing {what}"));
+        assert_eq!(e2.incidental()[0].find_attr("what"), Some(Value::Str("socket".into())));
+    }
+}
@@ -0,0 +1,201 @@
+use {
+    crate::{
+        format::Format,
+        value::Value,
+        Level,
+    },
+    chrono::{
+        DateTime,
+        FixedOffset,
+        Local,
+    },
+    std::{
+        backtrace::Backtrace,
+        borrow::Cow,
+        io::Write,
+        panic::Location,
+        sync::Arc,
+    },
+};
+
+/// A level of an event's error tree.  The root node holds the event message.
+#[derive(Debug, Clone)]
+pub struct EventNode {
+    pub message: String,
+    /// The format string the message was produced from, if created with a formatting
+    /// macro like `err!` or `info!`.  Named format arguments are included in the
+    /// attributes.  This can be used to group events with the same message but
+    /// different parameters.
+    pub template: Option<Cow<'static, str>>,
+    /// Where in the source this level was created.  Only set if enabled on the root
+    /// with `RootBuilder::locations`.
+    pub location: Option<&'static Location<'static>>,
+    /// A backtrace from where the error was created, if captured.  See
+    /// `Error::with_backtrace`.
+    pub backtrace: Option<Arc<Backtrace>>,
+    /// Attributes at this level, including attributes inherited from `Log` contexts
+    /// that weren't already output at a higher level.
+    pub attrs: Vec<(Cow<'static, str>, Value)>,
+    pub causes: Vec<EventNode>,
+    /// Errors that occurred while handling this error.
+    pub incidental: Vec<EventNode>,
+}
+
+impl EventNode {
+    /// Get the value of an attribute at this level.
+    pub fn attr(&self, key: &str) -> Option<&Value> {
+        return self.attrs.iter().find(|a| a.0 == key).map(|a| &a.1);
+    }
+
+    /// Remove the source locations from this node and all descendants.
+    pub(crate) fn clear_locations(&mut self) {
+        self.location = None;
+        for child in self.causes.iter_mut().chain(self.incidental.iter_mut()) {
+            child.clear_locations();
+        }
+    }
+
+    /// A hash of the structure of the error tree for grouping and deduplicating
+    /// repeated errors.  See `fingerprint_with`.
+    pub fn fingerprint(&self) -> u64 {
+        return self.fingerprint_with(&[]);
+    }
+
+    /// A hash of the structure of the error tree: the message templates (or messages,
+    /// if not created with a formatting macro), source locations, attribute keys, and
+    /// the layout of causes and incidental errors.  Attribute values are only included
+    /// for keys in `values`.
+    ///
+    /// The hash is stable across runs and builds (FNV-1a).
+    pub fn fingerprint_with(&self, values: &[&str]) -> u64 {
+        let mut hash = FNV_OFFSET;
+        self.hash_shape(&mut hash, values);
+        return hash;
+    }
+
+    fn hash_shape(&self, hash: &mut u64, values: &[&str]) {
+        hash_field(hash, b'm', self.template.as_deref().unwrap_or(&self.message).as_bytes());
+        if let Some(location) = self.location {
+            hash_field(hash, b'l', format!("{}:{}", location.file(), location.line()).as_bytes());
+        }
+        for (k, v) in &self.attrs {
+            hash_field(hash, b'k', k.as_bytes());
+            if values.contains(&k.as_ref()) {
+                hash_field(hash, b'v', v.to_string().as_bytes());
+            }
+        }
+        for (tag, children) in [(b'c', &self.causes), (b'i', &self.incidental)] {
+            for child in children {
+                hash_field(hash, tag, &[]);
+                child.hash_shape(hash, values);
+            }
+        }
+        hash_field(hash, b'e', &[]);
+    }
+
+    /// Describe the error tree using only messages, like `Outer (caused by: Inner
+    /// 1; Inner 2) (incidentally: Other)`.  This is intended for checking the
+    /// structure of errors in tests.
+    pub fn shape(&self) -> String {
+        let mut out = self.message.clone();
+        for (title, children) in [("caused by", &self.causes), ("incidentally", &self.incidental)] {
+            if children.is_empty() {
+                continue;
+            }
+            out.push_str(" (");
+            out.push_str(title);
+            out.push_str(": ");
+            out.push_str(&children.iter().map(|c| c.shape()).collect::<Vec<_>>().join("; "));
+            out.push(')');
+        }
+        return out;
+    }
+}
+
+#[cfg(test)]
+impl EventNode {
+    /// A node with just a message, attributes and causes, for tests.
+    pub(crate) fn new_test(message: &str, attrs: Vec<(&'static str, Value)>, causes: Vec<EventNode>) -> EventNode {
+        return EventNode {
+            message: message.to_string(),
+            template: None,
+            location: None,
+            backtrace: None,
+            attrs: attrs.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            causes: causes,
+            incidental: vec![],
+        };
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a hash of a single value, for short stable identifiers.
+pub(crate) fn hash_bytes(value: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET;
+    hash_field(&mut hash, b'b', value);
+    return hash;
+}
+
+/// Add a tagged, length-prefixed field to an FNV-1a hash.
+fn hash_field(hash: &mut u64, tag: u8, value: &[u8]) {
+    for b in [tag].iter().chain(&(value.len() as u64).to_le_bytes()).chain(value) {
+        *hash ^= *b as u64;
+        *hash = hash.wrapping_mul(FNV_PRIME);
+    }
+}
+
+/// A log message or fatal error, as passed to a `Sink`.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub level: Level,
+    pub time: DateTime<FixedOffset>,
+    pub node: EventNode,
+}
+
+impl Event {
+    pub(crate) fn new(level: Level, node: EventNode) -> Event {
+        return Event {
+            level: level,
+            time: Local::now().fixed_offset(),
+            node: node,
+        };
+    }
+}
+
+/// A destination for log events.  Set this on the root `Log` with
+/// `Log::new_root_with_sink` or `Log::new_root_builder`.
+pub trait Sink: Send + Sync {
+    /// Output an event.  Events are only passed to the sink if they pass the `Log`'s
+    /// level filter.
+    fn write(&self, event: &Event);
+
+    /// Make sure all written events have been output. Called before exiting in
+    /// `fatal`.
+    fn flush(&self) { }
+}
+
+/// Outputs events to stderr in the specified format.  The default sink for
+/// `Log::new_root` and `fatal` uses `TextFormat`.
+pub struct StderrSink {
+    format: Box<dyn Format>,
+}
+
+impl StderrSink {
+    pub fn new(format: impl Format + 'static) -> Self {
+        return Self { format: Box::new(format) };
+    }
+}
+
+impl Sink for StderrSink {
+    fn write(&self, event: &Event) {
+        let mut out = String::new();
+        self.format.render(event, &mut out);
+        _ = std::io::stderr().lock().write_all(out.as_bytes());
+    }
+
+    fn flush(&self) {
+        _ = std::io::stderr().flush();
+    }
+}
@@ -0,0 +1,121 @@
+use {
+    crate::{
+        sink::EventNode,
+        types::Error,
+    },
+    std::{
+        collections::HashSet,
+        fmt::Display,
+    },
+};
+
+/// Exposes a `loga::Error` as a `std::error::Error`, for boxing into `Box<dyn
+/// Error>` or passing to libraries that expect standard errors.  Create with
+/// `Error::into_std`.
+///
+/// Each layer displays its message and attributes, and `source()` returns the
+/// first cause.  Other causes and incidental errors aren't accessible via
+/// `source()`, but the full error can be retrieved with `into_inner`.
+#[derive(Debug)]
+pub struct StdError {
+    error: Error,
+    node: EventNode,
+    source: Option<Box<StdError>>,
+}
+
+impl StdError {
+    fn new(error: Error, node: EventNode) -> Self {
+        let source = match (error.0.causes.first(), node.causes.first()) {
+            (Some(error), Some(node)) => Some(Box::new(StdError::new(error.clone(), node.clone()))),
+            _ => None,
+        };
+        return Self {
+            error: error,
+            node: node,
+            source: source,
+        };
+    }
+
+    /// Get the wrapped error back.
+    pub fn into_inner(self) -> Error {
+        return self.error;
+    }
+}
+
+impl Display for StdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.node.message)?;
+        if self.node.attrs.is_empty() {
+            return Ok(());
+        }
+        f.write_str(" [")?;
+        for (i, (key, value)) in self.node.attrs.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, " {} = {}", key, value)?;
+        }
+        f.write_str(" ]")?;
+        return Ok(());
+    }
+}
+
+impl std::error::Error for StdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return self.source.as_ref().map(|s| s.as_ref() as &(dyn std::error::Error + 'static));
+    }
+}
+
+impl From<Error> for StdError {
+    fn from(value: Error) -> Self {
+        return value.into_std();
+    }
+}
+
+impl Error {
+    /// Convert to a type implementing `std::error::Error`.  See `StdError`.
+    pub fn into_std(self) -> StdError {
+        let node = self.build_event_node(&HashSet::new());
+        return StdError::new(self, node);
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl Error {
+    /// Convert an `anyhow::Error`, with a nested error for each error in its chain.
+    /// The original error is kept, see `Error::downcast_ref`.
+    #[track_caller]
+    pub fn from_anyhow(value: anyhow::Error) -> Error {
+        let mut e = crate::types::from_std_chain(value.as_ref());
+        e.0.original = Some(std::sync::Arc::from(Box::<dyn std::error::Error + Send + Sync>::from(value)));
+        return e;
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<Error> for anyhow::Error {
+    #[track_caller]
+    fn from(value: Error) -> Self {
+        return anyhow::Error::new(value.into_std());
+    }
+}
+
+#[cfg(feature = "eyre")]
+impl Error {
+    /// Convert an `eyre::Report`, with a nested error for each error in its chain.
+    /// The original error is kept, see `Error::downcast_ref`.
+    #[track_caller]
+    pub fn from_eyre(value: eyre::Report) -> Error {
+        let mut e = crate::types::from_std_chain(value.as_ref());
+        e.0.original = Some(std::sync::Arc::from(Box::<dyn std::error::Error + Send + Sync>::from(value)));
+        return e;
+    }
+}
+
+#[cfg(feature = "eyre")]
+impl From<Error> for eyre::Report {
+    #[track_caller]
+    fn from(value: Error) -> Self {
+        return eyre::Report::new(value.into_std());
+    }
+}
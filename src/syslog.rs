@@ -0,0 +1,493 @@
+use {
+    crate::{
+        conversion::ResultContext,
+        sink::{
+            hash_bytes,
+            Event,
+            EventNode,
+            Sink,
+        },
+        Error,
+    },
+    chrono::SecondsFormat,
+    std::{
+        io::Write,
+        net::{
+            SocketAddr,
+            TcpStream,
+            ToSocketAddrs,
+            UdpSocket,
+        },
+        sync::Mutex,
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+};
+#[cfg(unix)]
+use std::{
+    os::unix::net::UnixDatagram,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Syslog facility codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyslogFacility {
+    Kern = 0,
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+enum Transport {
+    #[cfg(unix)]
+    Unix(UnixDatagram, PathBuf),
+    Udp(UdpSocket),
+    Tcp(Vec<SocketAddr>, Mutex<TcpState>),
+}
+
+const TCP_TIMEOUT: Duration = Duration::from_secs(1);
+const TCP_MIN_BACKOFF: Duration = Duration::from_millis(100);
+const TCP_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+struct TcpState {
+    stream: Option<TcpStream>,
+    /// After a failed connection attempt, events are dropped without reconnecting
+    /// until this time.
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+/// Connect to the first reachable address with a short timeout, so a down server
+/// doesn't stall logging.
+fn tcp_connect(addrs: &[SocketAddr]) -> Result<TcpStream, std::io::Error> {
+    let mut last_error = std::io::Error::new(std::io::ErrorKind::InvalidInput, "No addresses to connect to");
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, TCP_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_write_timeout(Some(TCP_TIMEOUT))?;
+                return Ok(stream);
+            },
+            Err(e) => last_error = e,
+        }
+    }
+    return Err(last_error);
+}
+
+/// Outputs events as RFC 5424 syslog messages.
+///
+/// The event message is sent as the syslog MSG, and attributes are sent as
+/// STRUCTURED-DATA params in a single element (see `with_sd_id`). Causes and
+/// incidental errors are sent as params with indexed name prefixes, so the first
+/// cause's message is `cause.0.msg`, the first incidental error of the first cause
+/// is at `cause.0.incidental.0.msg`, etc.  Source locations, if enabled, are sent
+/// as `at` and backtraces, if captured, as `backtrace`.  Param names are limited to 32 characters
+/// by the RFC so longer names are shortened and end with a hash of the full name.
+pub struct SyslogSink {
+    transport: Transport,
+    facility: SyslogFacility,
+    hostname: String,
+    app_name: String,
+    sd_id: String,
+}
+
+impl SyslogSink {
+    fn new(transport: Transport) -> Self {
+        return Self {
+            transport: transport,
+            facility: SyslogFacility::User,
+            hostname: default_hostname(),
+            app_name: std::env::current_exe()
+                .ok()
+                .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_else(|| "-".to_string()),
+            sd_id: "loga@32473".to_string(),
+        };
+    }
+
+    /// Create a sink sending to the local syslog daemon at `/dev/log`.
+    #[cfg(unix)]
+    pub fn new_local() -> Result<Self, Error> {
+        return Self::new_unix("/dev/log");
+    }
+
+    /// Create a sink sending to a unix datagram socket at a custom path.
+    #[cfg(unix)]
+    pub fn new_unix(path: impl AsRef<Path>) -> Result<Self, Error> {
+        return Ok(
+            Self::new(
+                Transport::Unix(
+                    UnixDatagram::unbound().context("Error creating syslog client socket")?,
+                    path.as_ref().to_path_buf(),
+                ),
+            ),
+        );
+    }
+
+    /// Create a sink sending to a remote syslog server over UDP, one message per
+    /// datagram.
+    pub fn new_udp(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        let addrs = addr.to_socket_addrs().context("Error resolving syslog server address")?.collect::<Vec<_>>();
+        let Some(first) = addrs.first() else {
+            return Err(Error::from("Syslog server address resolved to no addresses"));
+        };
+        let bind_addr = if first.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(bind_addr).context("Error binding syslog client socket")?;
+        socket.connect(addrs.as_slice()).context("Error setting syslog server address")?;
+        return Ok(Self::new(Transport::Udp(socket)));
+    }
+
+    /// Create a sink sending to a remote syslog server over TCP, using octet-counting
+    /// framing (RFC 6587).  If the connection is lost it'll be reestablished on the
+    /// next event.  While the server is unreachable events are dropped, with
+    /// reconnection attempts backing off exponentially up to 30 seconds apart.
+    pub fn new_tcp(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        let addrs = addr.to_socket_addrs().context("Error resolving syslog server address")?.collect::<Vec<_>>();
+        let stream = tcp_connect(&addrs).context("Error connecting to syslog server")?;
+        return Ok(Self::new(Transport::Tcp(addrs, Mutex::new(TcpState {
+            stream: Some(stream),
+            retry_at: None,
+            backoff: TCP_MIN_BACKOFF,
+        }))));
+    }
+
+    /// Set the facility sent with each message, defaults to `User`.
+    pub fn with_facility(mut self, facility: SyslogFacility) -> Self {
+        self.facility = facility;
+        return self;
+    }
+
+    /// Set the APP-NAME sent with each message, defaults to the executable name.
+    pub fn with_app_name(mut self, app_name: impl ToString) -> Self {
+        self.app_name = app_name.to_string();
+        return self;
+    }
+
+    /// Set the HOSTNAME sent with each message, defaults to the system hostname.
+    pub fn with_hostname(mut self, hostname: impl ToString) -> Self {
+        self.hostname = hostname.to_string();
+        return self;
+    }
+
+    /// Set the SD-ID of the STRUCTURED-DATA element holding the attributes.  This
+    /// defaults to `loga@32473` which uses the example private enterprise number from
+    /// RFC 5612 - you may want to replace it with your own.
+    pub fn with_sd_id(mut self, sd_id: impl ToString) -> Self {
+        self.sd_id = sd_id.to_string();
+        return self;
+    }
+
+    fn render(&self, event: &Event) -> String {
+        let mut out = format!(
+            "<{}>1 {} {} {} {} - ",
+            self.facility as u8 * 8 + event.level.syslog_severity(),
+            event.time.to_rfc3339_opts(SecondsFormat::Micros, false),
+            header_field(&self.hostname, 255),
+            header_field(&self.app_name, 48),
+            std::process::id()
+        );
+        out.push('[');
+        out.push_str(&header_field(&self.sd_id, MAX_SD_NAME));
+        write_node(&mut out, "", &event.node, false);
+        out.push_str("] ");
+        out.push_str(&event.node.message);
+        return out;
+    }
+}
+
+impl Sink for SyslogSink {
+    fn write(&self, event: &Event) {
+        let message = self.render(event);
+        match &self.transport {
+            #[cfg(unix)]
+            Transport::Unix(socket, path) => {
+                _ = socket.send_to(message.as_bytes(), path);
+            },
+            Transport::Udp(socket) => {
+                _ = socket.send(message.as_bytes());
+            },
+            Transport::Tcp(addrs, state) => {
+                let framed = format!("{} {}", message.len(), message);
+                let mut state = state.lock().unwrap();
+                if let Some(s) = state.stream.as_mut() {
+                    if s.write_all(framed.as_bytes()).is_ok() {
+                        return;
+                    }
+                    state.stream = None;
+                }
+
+                // Reconnect and retry once, unless backing off after a failed attempt
+                let now = Instant::now();
+                if state.retry_at.is_some_and(|t| now < t) {
+                    return;
+                }
+                let connected = tcp_connect(addrs).and_then(|mut s| {
+                    s.write_all(framed.as_bytes())?;
+                    return Ok(s);
+                });
+                match connected {
+                    Ok(s) => {
+                        state.stream = Some(s);
+                        state.retry_at = None;
+                        state.backoff = TCP_MIN_BACKOFF;
+                    },
+                    Err(_) => {
+                        state.retry_at = Some(now + state.backoff);
+                        state.backoff = (state.backoff * 2).min(TCP_MAX_BACKOFF);
+                    },
+                }
+            },
+        }
+    }
+
+    fn flush(&self) {
+        if let Transport::Tcp(_, state) = &self.transport {
+            if let Some(s) = state.lock().unwrap().stream.as_mut() {
+                _ = s.flush();
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn default_hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe {
+        libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len())
+    } != 0 {
+        return "-".to_string();
+    }
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    return String::from_utf8_lossy(&buf[..len]).to_string();
+}
+
+#[cfg(not(unix))]
+fn default_hostname() -> String {
+    return std::env::var("COMPUTERNAME").unwrap_or_else(|_| "-".to_string());
+}
+
+/// Header fields (and SD names) are limited to printable ASCII excluding space, `=`,
+/// `]` and `"`, with a maximum length.
+fn header_field(value: &str, max_len: usize) -> String {
+    let out =
+        value
+            .chars()
+            .map(|c| if c.is_ascii_graphic() && c != '=' && c != ']' && c != '"' {
+                c
+            } else {
+                '_'
+            })
+            .take(max_len)
+            .collect::<String>();
+    if out.is_empty() {
+        return "-".to_string();
+    }
+    return out;
+}
+
+fn write_node(out: &mut String, prefix: &str, node: &EventNode, write_message: bool) {
+    if write_message {
+        write_param(out, &format!("{}msg", prefix), &node.message);
+    }
+    if let Some(location) = node.location {
+        write_param(out, &format!("{}at", prefix), &format!("{}:{}", location.file(), location.line()));
+    }
+    if let Some(backtrace) = &node.backtrace {
+        write_param(out, &format!("{}backtrace", prefix), &backtrace.to_string());
+    }
+    for (k, v) in &node.attrs {
+        write_param(out, &format!("{}{}", prefix, k), &v.to_string());
+    }
+    for (key, children) in [("cause", &node.causes), ("incidental", &node.incidental)] {
+        for (i, child) in children.iter().enumerate() {
+            write_node(out, &format!("{}{}.{}.", prefix, key, i), child, true);
+        }
+    }
+}
+
+/// The maximum length of SD-IDs and PARAM-NAMEs.
+const MAX_SD_NAME: usize = 32;
+
+/// Shorten a param name to the RFC's limit, keeping it unique by replacing the end
+/// with a hash of the full name.
+fn param_name(name: &str) -> String {
+    let out = header_field(name, usize::MAX);
+    if out.len() <= MAX_SD_NAME {
+        return out;
+    }
+    let hash = format!("_{:08x}", hash_bytes(name.as_bytes()) as u32);
+    return format!("{}{}", &out[..MAX_SD_NAME - hash.len()], hash);
+}
+
+fn write_param(out: &mut String, name: &str, value: &str) {
+    out.push(' ');
+    out.push_str(&param_name(name));
+    out.push_str("=\"");
+    for c in value.chars() {
+        if c == '"' || c == '\\' || c == ']' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            SyslogFacility,
+            SyslogSink,
+        },
+        crate::{
+            sink::{
+                Event,
+                EventNode,
+                Sink,
+            },
+            value::Value,
+            INFO,
+            WARN,
+        },
+        chrono::DateTime,
+        std::{
+            io::Read,
+            backtrace::Backtrace,
+            net::{
+                TcpListener,
+                UdpSocket,
+            },
+            sync::Arc,
+            time::Duration,
+        },
+    };
+
+    /// Split the params of the first STRUCTURED-DATA element, unescaping values.
+    fn params(message: &str) -> Vec<(String, String)> {
+        let mut out = vec![];
+        let mut chars = message[message.find('[').unwrap() ..].chars().skip_while(|c| *c != ' ').peekable();
+        while chars.peek() == Some(&' ') {
+            chars.next();
+            let name = chars.by_ref().take_while(|c| *c != '=').collect::<String>();
+            assert_eq!(chars.next(), Some('"'));
+            let mut value = String::new();
+            loop {
+                match chars.next().unwrap() {
+                    '\\' => value.push(chars.next().unwrap()),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+            out.push((name, value));
+        }
+        assert_eq!(chars.next(), Some(']'));
+        return out;
+    }
+
+    #[test]
+    fn udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let sink =
+            SyslogSink::new_udp(server.local_addr().unwrap())
+                .unwrap()
+                .with_facility(SyslogFacility::Local0)
+                .with_hostname("host")
+                .with_app_name("app");
+        let mut leaf = EventNode::new_test("Leaf", vec![("a", Value::U64(1)), ("b", Value::U64(2))], vec![]);
+        for _ in 0 .. 4 {
+            leaf = EventNode::new_test("Layer", vec![], vec![leaf]);
+        }
+        let backtrace = Arc::new(Backtrace::force_capture());
+        leaf.backtrace = Some(backtrace.clone());
+        sink.write(&Event {
+            level: WARN,
+            time: DateTime::parse_from_rfc3339("2024-01-02T03:04:05+00:00").unwrap(),
+            node: EventNode::new_test("Hello", vec![("quote", Value::Str("a\"b\\c]d".into()))], vec![leaf]),
+        });
+        let mut buf = vec![0u8; 65536];
+        let len = server.recv(&mut buf).unwrap();
+        let message = String::from_utf8(buf[..len].to_vec()).unwrap();
+        assert!(
+            message.starts_with(
+                &format!(
+                    "<132>1 2024-01-02T03:04:05.000000+00:00 host app {} - [loga@32473 quote=\"a\\\"b\\\\c\\]d\" ",
+                    std::process::id()
+                ),
+            ),
+            "{}",
+            message
+        );
+        assert!(message.ends_with("] Hello"));
+        let params = params(&message);
+        assert_eq!(params[0], ("quote".to_string(), "a\"b\\c]d".to_string()));
+        assert_eq!(params[1], ("cause.0.msg".to_string(), "Layer".to_string()));
+        assert_eq!(params[2], ("cause.0.backtrace".to_string(), backtrace.to_string()));
+        for (name, _) in &params {
+            assert!(name.len() <= 32);
+        }
+
+        // Names past 32 characters (the 4th layer message and the leaf fields) are
+        // shortened but must stay distinct
+        let leaf_params =
+            params.iter().filter(|(name, _)| name.starts_with("cause.0.cause.0.cause.0_")).collect::<Vec<_>>();
+        assert_eq!(leaf_params.len(), 4);
+        let mut names = leaf_params.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 4);
+        let mut values = leaf_params.iter().map(|(_, value)| value.as_str()).collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, ["1", "2", "Layer", "Leaf"]);
+    }
+
+    #[test]
+    fn tcp_framing() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink = SyslogSink::new_tcp(server.local_addr().unwrap()).unwrap();
+        let (mut stream, _) = server.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        sink.write(&Event::new(INFO, EventNode::new_test("First ü", vec![], vec![])));
+        sink.write(&Event::new(INFO, EventNode::new_test("Second\nline", vec![], vec![])));
+        sink.flush();
+        drop(sink);
+        let mut data = vec![];
+        stream.read_to_end(&mut data).unwrap();
+        let mut messages = vec![];
+        let mut at = data.as_slice();
+        while !at.is_empty() {
+            let space = at.iter().position(|b| *b == b' ').unwrap();
+            let len = std::str::from_utf8(&at[..space]).unwrap().parse::<usize>().unwrap();
+            messages.push(String::from_utf8(at[space + 1 .. space + 1 + len].to_vec()).unwrap());
+            at = &at[space + 1 + len..];
+        }
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("<14>1 "));
+        assert!(messages[0].ends_with("] First ü"));
+        assert!(messages[1].ends_with("] Second\nline"));
+    }
+}
@@ -1,36 +1,74 @@
 use {
     crate::{
+        capture::Captured,
+        common::Attrs,
         ea,
+        format::{
+            Format,
+            TextFormat,
+        },
+        sink::{
+            Event,
+            EventNode,
+            Sink,
+            StderrSink,
+        },
+        background,
+        rate_limit::{
+            self,
+            RateLimiter,
+        },
+        value::Value,
         Level,
+        FATAL,
     },
-    chrono::Local,
-    console::{
-        measure_text_width,
-        Style,
+    chrono::{
+        DateTime,
+        FixedOffset,
+        Local,
     },
     std::{
-        borrow::Cow,
-        collections::{
-            HashMap,
-            HashSet,
+        backtrace::{
+            Backtrace,
+            BacktraceStatus,
         },
+        borrow::Cow,
+        collections::HashSet,
         fmt::Display,
+        panic::Location,
+        process::exit,
         sync::Arc,
-    },
-    textwrap::{
-        wrap,
-        Options,
+        time::Duration,
     },
 };
 
 #[derive(Debug, Clone)]
 pub(crate) struct Error_ {
     pub message: String,
-    pub attrs: HashMap<&'static str, String>,
+    pub attrs: Attrs,
     pub context: Vec<Log>,
     pub causes: Vec<Error>,
     /// Errors that occur during error handling
     pub(crate) incidental: Vec<Error>,
+    /// The format string used to produce the message, if created with a formatting
+    /// macro like `err!`.
+    pub(crate) template: Option<Cow<'static, str>>,
+    /// Where this layer was created.
+    pub(crate) location: Option<&'static Location<'static>>,
+    /// Only captured for leaf errors, see `Error::with_backtrace`.
+    pub(crate) backtrace: Option<Arc<Backtrace>>,
+    /// The foreign error this layer was converted from.
+    pub(crate) original: Option<Arc<dyn std::error::Error + Send + Sync>>,
+}
+
+/// Capture a backtrace if enabled by the `RUST_LIB_BACKTRACE` or `RUST_BACKTRACE`
+/// environment variables.
+pub(crate) fn capture_backtrace() -> Option<Arc<Backtrace>> {
+    let backtrace = Backtrace::capture();
+    if backtrace.status() != BacktraceStatus::Captured {
+        return None;
+    }
+    return Some(Arc::new(backtrace));
 }
 
 /// A comprehensive structural error type, intended exclusively for human
@@ -38,30 +76,182 @@ pub(crate) struct Error_ {
 #[derive(Debug, Clone)]
 pub struct Error(pub(crate) Box<Error_>);
 
-pub(crate) struct RenderNodeBranch<'a> {
-    pub(crate) title: Cow<'a, str>,
-    pub(crate) children: Vec<RenderNode<'a>>,
-}
-
-pub(crate) enum RenderNode<'a> {
-    KVLeaf {
-        key: &'a str,
-        value: &'a String,
-    },
-    Branch(RenderNodeBranch<'a>),
-}
-
 impl Error {
+    #[track_caller]
     pub fn from(x: impl Display) -> Error {
         return Error(Box::new(Error_ {
             message: x.to_string(),
-            attrs: HashMap::new(),
+            attrs: Attrs::new(),
             context: vec![],
             causes: vec![],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: capture_backtrace(),
+            original: None,
         }));
     }
 
+    #[doc(hidden)]
+    pub fn __with_template(mut self, template: &'static str) -> Error {
+        self.0.template = Some(Cow::Borrowed(template));
+        return self;
+    }
+
+    /// The message of this layer of the error.
+    pub fn message(&self) -> &str {
+        return &self.0.message;
+    }
+
+    /// The format string the message was produced from, if created with a formatting
+    /// macro like `err!`.
+    pub fn template(&self) -> Option<&str> {
+        return self.0.template.as_deref();
+    }
+
+    /// Where this layer of the error was created.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        return self.0.location;
+    }
+
+    /// The attributes of this layer of the error, including attributes inherited from
+    /// `Log` contexts.  Unlike in output, context attributes already present at a
+    /// higher level aren't omitted.
+    pub fn attrs(&self) -> Attrs {
+        return Attrs(self.gather_attrs(&HashSet::new(), &mut HashSet::new()));
+    }
+
+    /// The errors that caused this error.
+    pub fn causes(&self) -> &[Error] {
+        return &self.0.causes;
+    }
+
+    /// Errors that occurred while handling this error.
+    pub fn incidental(&self) -> &[Error] {
+        return &self.0.incidental;
+    }
+
+    /// Find the value of an attribute anywhere in the error, including attributes from
+    /// `Log` contexts.  If the attribute is at multiple levels, the value closest to
+    /// the top (depth first, causes before incidental errors) is returned.
+    pub fn find_attr(&self, key: &str) -> Option<Value> {
+        return self.attrs_all(key).into_iter().next();
+    }
+
+    /// Get every value of an attribute in the error, including attributes from `Log`
+    /// contexts, in depth first order.  Shadowed attributes and context attributes
+    /// already at a higher level are skipped, the same as in output.
+    pub fn attrs_all(&self, key: &str) -> Vec<Value> {
+        fn walk(out: &mut Vec<Value>, node: &EventNode, key: &str) {
+            if let Some(value) = node.attr(key) {
+                out.push(value.clone());
+            }
+            for child in node.causes.iter().chain(node.incidental.iter()) {
+                walk(out, child, key);
+            }
+        }
+
+        let mut out = vec![];
+        walk(&mut out, &self.build_event_node(&HashSet::new()), key);
+        return out;
+    }
+
+    /// A hash of the structure of the error for grouping and deduplicating repeated
+    /// errors, see `EventNode::fingerprint_with`.  This includes source locations, which
+    /// aren't included in the fingerprint of events unless enabled with
+    /// `RootBuilder::locations`.
+    pub fn fingerprint(&self) -> u64 {
+        return self.build_event_node(&HashSet::new()).fingerprint();
+    }
+
+    /// Like `fingerprint` but including the values of the attributes in `values`.
+    pub fn fingerprint_with(&self, values: &[&str]) -> u64 {
+        return self.build_event_node(&HashSet::new()).fingerprint_with(values);
+    }
+
+    /// Iterate this error and all nested errors depth first: this error, then each
+    /// cause and its descendants, then each incidental error and its descendants.
+    pub fn walk(&self) -> Walk<'_> {
+        return Walk { stack: vec![self] };
+    }
+
+    /// Capture a backtrace for this error, regardless of the `RUST_LIB_BACKTRACE` and
+    /// `RUST_BACKTRACE` environment variables.  Backtraces are otherwise only captured
+    /// when creating new errors (not context layers or log messages) if enabled by
+    /// those variables or `RootBuilder::backtraces`.
+    pub fn with_backtrace(mut self) -> Error {
+        if self.0.backtrace.is_none() {
+            self.0.backtrace = Some(Arc::new(Backtrace::force_capture()));
+        }
+        return self;
+    }
+
+    /// Convert a standard error, the same as `From`.  This is useful where the inherent
+    /// `Error::from` (which only keeps the message) would be picked instead, like
+    /// `std::fs::read(path).map_err(Error::from_std)`.
+    #[track_caller]
+    pub fn from_std<T: std::error::Error + Send + Sync + 'static>(value: T) -> Error {
+        let mut e = from_std_chain(&value);
+        e.0.attrs.insert("error_type", std::any::type_name::<T>());
+        e.0.original = Some(Arc::new(value));
+        return e;
+    }
+
+    /// Find an error of type `T` this error was converted from (with `?`, `context`,
+    /// `from_std`, `from_anyhow`, etc.), searching this error and its causes depth first.
+    /// This also checks the `source()` chains of the original errors.  Use like
+    /// `e.downcast_ref::<std::io::Error>()`.
+    pub fn downcast_ref<T: std::error::Error + 'static>(&self) -> Option<&T> {
+        return self.find_cause::<T>()?.original_as::<T>();
+    }
+
+    /// Find an error of type `T` in the `source()` chain of the original error at this
+    /// layer.
+    fn original_as<T: std::error::Error + 'static>(&self) -> Option<&T> {
+        let mut at: Option<&(dyn std::error::Error + 'static)> = Some(self.0.original.as_ref()?.as_ref());
+        while let Some(at1) = at {
+            if let Some(found) = at1.downcast_ref::<T>() {
+                return Some(found);
+            }
+            at = at1.source();
+        }
+        return None;
+    }
+
+    /// Like `downcast_ref` but returns the layer of this error (or its causes) that was
+    /// converted from the error of type `T`, for example to get its attributes.
+    pub fn find_cause<T: std::error::Error + 'static>(&self) -> Option<&Error> {
+        if self.original_as::<T>().is_some() {
+            return Some(self);
+        }
+        for cause in &self.0.causes {
+            if let Some(found) = cause.find_cause::<T>() {
+                return Some(found);
+            }
+        }
+        return None;
+    }
+
+    /// Find the root of the first rooted `Log` context of this error or its causes and
+    /// incidental errors, depth first.
+    pub(crate) fn find_root(&self) -> Option<&LogRoot> {
+        for context in &self.0.context {
+            let mut at = Some(context);
+            while let Some(at1) = at {
+                if let Some(root) = &at1.0.root {
+                    return Some(root);
+                }
+                at = at1.0.parent.as_ref();
+            }
+        }
+        for child in self.0.causes.iter().chain(self.0.incidental.iter()) {
+            if let Some(root) = child.find_root() {
+                return Some(root);
+            }
+        }
+        return None;
+    }
+
     /// Extend the base error with a new incidental (occurred while handling the base
     /// error) error.  Use like `e.also(log, new_e);`.
     pub fn also(mut self, incidental: Error) -> Error {
@@ -69,20 +259,21 @@ impl Error {
         return self;
     }
 
-    pub(crate) fn build_render_nodes<'a>(&'a self, seen_contexts: &HashSet<*const Log_>) -> RenderNodeBranch<'a> {
-        let message;
-        let mut children = vec![];
-        let mut sub_seen_contexts = seen_contexts.clone();
-        message = self.0.message.to_string();
+    /// Get this layer's attributes followed by the attributes of its `Log` contexts,
+    /// skipping shadowed attributes and contexts in `seen_contexts`.  The contexts
+    /// used are added to `sub_seen_contexts`.
+    fn gather_attrs(
+        &self,
+        seen_contexts: &HashSet<*const Log_>,
+        sub_seen_contexts: &mut HashSet<*const Log_>,
+    ) -> Vec<(Cow<'static, str>, Value)> {
+        let mut attrs = vec![];
         let mut seen_attrs = HashSet::new();
-        for a in &self.0.attrs {
-            if !seen_attrs.insert(*a.0) {
+        for (k, v) in &self.0.attrs {
+            if !seen_attrs.insert(k.as_ref()) {
                 continue;
             }
-            children.push(RenderNode::KVLeaf {
-                key: a.0,
-                value: a.1,
-            })
+            attrs.push((k.clone(), v.clone()));
         }
         for context in &self.0.context {
             let mut at = Some(context);
@@ -91,65 +282,57 @@ impl Error {
                 if seen_contexts.contains(&addr) {
                     break;
                 }
-                for a in &at1.0.attrs {
-                    if !seen_attrs.insert(*a.0) {
+                for (k, v) in &at1.0.attrs {
+                    if !seen_attrs.insert(k.as_ref()) {
                         continue;
                     }
-                    children.push(RenderNode::KVLeaf {
-                        key: a.0,
-                        value: a.1,
-                    })
+                    attrs.push((k.clone(), v.clone()));
                 }
                 sub_seen_contexts.insert(at1.0.as_ref());
                 at = at1.0.parent.as_ref();
             }
         }
-        if self.0.causes.len() > 0 {
-            children.push(RenderNode::Branch(RenderNodeBranch {
-                title: "Caused by:".into(),
-                children: self
-                    .0
-                    .causes
-                    .iter()
-                    .map(|x| RenderNode::Branch(x.build_render_nodes(&sub_seen_contexts)))
-                    .collect(),
-            }));
-        }
-        if self.0.incidental.len() > 0 {
-            children.push(RenderNode::Branch(RenderNodeBranch {
-                title: "Incidentally:".into(),
-                children: self
-                    .0
-                    .incidental
-                    .iter()
-                    .map(|x| RenderNode::Branch(x.build_render_nodes(&sub_seen_contexts)))
-                    .collect(),
-            }));
-        }
-        return RenderNodeBranch {
-            title: message.into(),
-            children: children,
+        return attrs;
+    }
+
+    pub(crate) fn build_event_node(&self, seen_contexts: &HashSet<*const Log_>) -> EventNode {
+        let mut sub_seen_contexts = seen_contexts.clone();
+        let attrs = self.gather_attrs(seen_contexts, &mut sub_seen_contexts);
+        return EventNode {
+            message: self.0.message.clone(),
+            template: self.0.template.clone(),
+            location: self.0.location,
+            backtrace: self.0.backtrace.clone(),
+            attrs: attrs,
+            causes: self.0.causes.iter().map(|x| x.build_event_node(&sub_seen_contexts)).collect(),
+            incidental: self.0.incidental.iter().map(|x| x.build_event_node(&sub_seen_contexts)).collect(),
         };
     }
 
     /// Return a new error adding a simple string message as a layer of context.
+    #[track_caller]
     pub fn context(self, message: impl ToString) -> Error {
         return Error(Box::new(Error_ {
             message: message.to_string(),
-            attrs: HashMap::new(),
+            attrs: Attrs::new(),
             context: vec![],
             causes: vec![self],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
     /// Return a new error adding a layer of context with a message and attributes.
+    #[track_caller]
     pub fn context_with(
         self,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Error {
-        let mut new_attrs = HashMap::new();
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Error(Box::new(Error_ {
             message: message.to_string(),
@@ -157,30 +340,40 @@ impl Error {
             context: vec![],
             causes: vec![self],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
     /// Return a new error adding a layer of context including all attributes in the
     /// provided log along with the specified message.
+    #[track_caller]
     pub fn stack_context(self, log: &Log, message: impl ToString) -> Error {
         return Error(Box::new(Error_ {
             message: message.to_string(),
-            attrs: HashMap::new(),
+            attrs: Attrs::new(),
             context: vec![log.clone()],
             causes: vec![self],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
     /// Return a new error adding a layer of context including all attributes in the
     /// provided log along with the specified message and new attributes.
+    #[track_caller]
     pub fn stack_context_with(
         self,
         log: &Log,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Error {
-        let mut new_attrs = HashMap::new();
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Error(Box::new(Error_ {
             message: message.to_string(),
@@ -188,85 +381,116 @@ impl Error {
             context: vec![log.clone()],
             causes: vec![self],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let node = RenderNode::Branch(self.build_render_nodes(&HashSet::new()));
-        let mut stack = vec![(true, 0, &node)];
-        while let Some((descending, index, top)) = stack.pop() {
-            match top {
-                RenderNode::KVLeaf { key, value } => {
-                    if index > 0 {
+        fn write_node(f: &mut std::fmt::Formatter<'_>, node: &EventNode) -> std::fmt::Result {
+            f.write_str(&node.message)?;
+            f.write_str(" [")?;
+            let mut index = 0;
+            for (key, value) in &node.attrs {
+                if index > 0 {
+                    f.write_str(",")?;
+                }
+                f.write_str(" ")?;
+                f.write_str(key)?;
+                f.write_str(" = ")?;
+                write!(f, "{}", value)?;
+                index += 1;
+            }
+            for (title, children) in [("Caused by:", &node.causes), ("Incidentally:", &node.incidental)] {
+                if children.is_empty() {
+                    continue;
+                }
+                if index > 0 {
+                    f.write_str(",")?;
+                }
+                f.write_str(" ")?;
+                f.write_str(title)?;
+                f.write_str(" [")?;
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
                         f.write_str(",")?;
                     }
                     f.write_str(" ")?;
-                    f.write_str(key)?;
-                    f.write_str(" = ")?;
-                    f.write_str(&value)?;
-                },
-                RenderNode::Branch(b) => {
-                    if descending {
-                        f.write_str(&b.title)?;
-                        f.write_str(" [")?;
-                        stack.extend(b.children.iter().enumerate().rev().map(|(i, e)| (true, i, e)));
-                    } else {
-                        f.write_str(" ]")?;
-                    }
-                },
+                    write_node(f, child)?;
+                }
+                f.write_str(" ]")?;
+                index += 1;
             }
+            f.write_str(" ]")?;
+            return Ok(());
         }
-        return Ok(());
+
+        return write_node(f, &self.build_event_node(&HashSet::new()));
     }
 }
 
-impl<T: std::error::Error> From<T> for Error {
+/// Iterator over an error and its nested errors, see `Error::walk`.
+pub struct Walk<'a> {
+    stack: Vec<&'a Error>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = &'a Error;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let top = self.stack.pop()?;
+        self.stack.extend(top.0.incidental.iter().rev());
+        self.stack.extend(top.0.causes.iter().rev());
+        return Some(top);
+    }
+}
+
+/// Converts the error and its `source()` chain to nested errors.  The converted
+/// error has the Rust type name as the attribute `error_type` (the types of the
+/// sources aren't known).  The original error is kept for `Error::downcast_ref`.
+///
+/// Errors that aren't `Send + Sync + 'static`, like a `PoisonError` holding a
+/// `MutexGuard`, need to be converted via their message, like
+/// `m.lock().map_err(loga::err)?`.
+impl<T: std::error::Error + Send + Sync + 'static> From<T> for Error {
+    #[track_caller]
     fn from(value: T) -> Self {
-        return Error::from(value);
+        return Error::from_std(value);
     }
 }
 
-pub(crate) fn log(body_color: Style, level_color: Style, level_text: &str, node: RenderNodeBranch) {
-    let highlight_style = Style::new().for_stderr().blue();
-    let dark_style = Style::new().for_stderr().dim();
-    let mut out = String::new();
-    let mut stack = node.children.iter().rev().map(|e| (0, e)).collect::<Vec<_>>();
-    while let Some((indent_count, top)) = stack.pop() {
-        let indent = "  ".repeat(indent_count);
-        match top {
-            RenderNode::KVLeaf { key, value } => {
-                let key = format!("- {} = ", key);
-                for line in wrap(
-                    &value,
-                    Options::with_termwidth()
-                        .initial_indent(&format!("{}{}", indent, key))
-                        .subsequent_indent(&format!("{}{}", indent, " ".repeat(measure_text_width(&key)))),
-                ) {
-                    out.push_str(&dark_style.apply_to(line).to_string());
-                    out.push('\n');
-                }
-            },
-            RenderNode::Branch(b) => {
-                for line in wrap(
-                    &format!("{}", b.title),
-                    Options::with_termwidth().initial_indent(&indent).subsequent_indent(&indent),
-                ) {
-                    out.push_str(&highlight_style.apply_to(line).to_string());
-                    out.push('\n');
-                }
-                stack.extend(b.children.iter().rev().map(|e| (indent_count + 1, e)));
-            },
-        }
+/// Convert a foreign error to an error with a nested error for each error in its
+/// `source()` chain.
+#[track_caller]
+pub(crate) fn from_std_chain(value: &dyn std::error::Error) -> Error {
+    let mut sources = vec![];
+    let mut at = value.source();
+    while let Some(source) = at {
+        sources.push(Error(Box::new(Error_ {
+            message: source.to_string(),
+            attrs: Attrs::new(),
+            context: vec![],
+            causes: vec![],
+            incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
+        })));
+        at = source.source();
+    }
+    let mut cause = None;
+    while let Some(mut source) = sources.pop() {
+        source.0.causes.extend(cause);
+        cause = Some(source);
     }
-    eprint!(
-        "{} {}: {}\n{}",
-        body_color.apply_to(Local::now().to_rfc3339()),
-        level_color.apply_to(level_text),
-        node.title,
-        out
-    );
+    let mut e = Error::from(value);
+    e.0.causes.extend(cause);
+    return e;
 }
 
 /// A store of context with methods for logging and creating errors expressing that
@@ -277,16 +501,143 @@ pub struct Log(Arc<Log_>);
 #[derive(Debug)]
 pub(crate) struct Log_ {
     pub(crate) parent: Option<Log>,
-    pub(crate) attrs: HashMap<&'static str, String>,
-    pub(crate) log_from: Option<Level>,
+    pub(crate) attrs: Attrs,
+    /// Only set on rooted (logging) contexts.
+    pub(crate) root: Option<LogRoot>,
+}
+
+#[derive(Clone)]
+pub(crate) struct LogRoot {
+    /// The minimum level for this context and its forks, in addition to the sink
+    /// levels.
+    pub(crate) log_from: Level,
+    pub(crate) config: Arc<RootConfig>,
+    /// Shared by this context and its forks, unless replaced.
+    pub(crate) rate_limit: Option<Arc<RateLimiter>>,
+}
+
+pub(crate) struct RootConfig {
+    /// Sinks and the minimum level of events each sink receives.
+    pub(crate) sinks: Vec<(Level, Box<dyn Sink>)>,
+    pub(crate) clock: Option<Box<dyn Fn() -> DateTime<FixedOffset> + Send + Sync>>,
+    pub(crate) locations: bool,
+    pub(crate) backtraces: bool,
+}
+
+impl RootConfig {
+    pub(crate) fn now(&self) -> DateTime<FixedOffset> {
+        match &self.clock {
+            Some(clock) => return clock(),
+            None => return Local::now().fixed_offset(),
+        }
+    }
+
+    pub(crate) fn write(&self, level: Level, node: EventNode) {
+        let event = Event {
+            level: level,
+            time: self.now(),
+            node: node,
+        };
+        for (sink_log_from, sink) in &self.sinks {
+            if level >= *sink_log_from {
+                sink.write(&event);
+            }
+        }
+    }
+
+    pub(crate) fn build_event_node(&self, e: &Error) -> EventNode {
+        let mut node = e.build_event_node(&HashSet::new());
+        if !self.locations {
+            node.clear_locations();
+        }
+        return node;
+    }
+}
+
+impl std::fmt::Debug for LogRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.debug_struct("LogRoot").field("log_from", &self.log_from).finish_non_exhaustive();
+    }
+}
+
+/// Configuration for a new root `Log`, see `Log::new_root_builder`.
+pub struct RootBuilder {
+    sinks: Vec<(Level, Box<dyn Sink>)>,
+    clock: Option<Box<dyn Fn() -> DateTime<FixedOffset> + Send + Sync>>,
+    locations: bool,
+    backtraces: bool,
+    rate_limit: Option<(usize, Duration)>,
+}
+
+impl RootBuilder {
+    /// Add a sink that receives events at or above `log_from`.
+    pub fn sink(mut self, log_from: Level, sink: impl Sink + 'static) -> Self {
+        self.sinks.push((log_from, Box::new(sink)));
+        return self;
+    }
+
+    /// Use a custom source for event timestamps, for example to produce identical
+    /// output across runs in snapshot tests.
+    pub fn clock(mut self, clock: impl Fn() -> DateTime<FixedOffset> + Send + Sync + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        return self;
+    }
+
+    /// Include the source location where each error layer was created in output, like
+    /// `at src/main.rs:123`.  Off by default.
+    pub fn locations(mut self, enabled: bool) -> Self {
+        self.locations = enabled;
+        return self;
+    }
+
+    /// Capture backtraces for errors created with `Log::err` and `Log::err_with` on
+    /// this context and its forks, regardless of the `RUST_LIB_BACKTRACE` and
+    /// `RUST_BACKTRACE` environment variables.  Backtraces aren't captured for log
+    /// messages (`Log::log`, `info!`, etc).  Off by default.
+    pub fn backtraces(mut self, enabled: bool) -> Self {
+        self.backtraces = enabled;
+        return self;
+    }
+
+    /// Output at most `max` events with the same fingerprint (see
+    /// `EventNode::fingerprint`) per `interval`, suppressing the rest.  When an
+    /// interval with suppressed events ends, a summary event like `Previous message
+    /// repeated 12 times` is output with the count in the `count` attribute.  The
+    /// summary is output when the same event is next logged after the interval ends,
+    /// when the `Log`s using the limit are dropped, or before exiting in `fatal`.
+    ///
+    /// To limit only some events, see `Log::fork_with_rate_limit`.
+    pub fn rate_limit(mut self, max: usize, interval: Duration) -> Self {
+        self.rate_limit = Some((max, interval));
+        return self;
+    }
+
+    pub fn build(self) -> Log {
+        let log_from = self.sinks.iter().map(|s| s.0).min().unwrap_or(FATAL);
+        let config = Arc::new(RootConfig {
+            sinks: self.sinks,
+            clock: self.clock,
+            locations: self.locations,
+            backtraces: self.backtraces,
+        });
+        return Log(Arc::new(Log_ {
+            parent: None,
+            attrs: Attrs::new(),
+            root: Some(LogRoot {
+                log_from: log_from,
+                rate_limit: self.rate_limit.map(|(max, interval)| RateLimiter::new(max, interval, config.clone())),
+                config: config,
+            }),
+        }));
+    }
 }
 
 impl Default for Log {
     fn default() -> Self {
         return Self(Arc::new(Log_ {
             parent: None,
-            attrs: HashMap::new(),
-            log_from: None,
+            attrs: Attrs::new(),
+            root: None,
         }));
     }
 }
@@ -298,117 +649,200 @@ impl Log {
         return Self::default();
     }
 
+    /// Create a new logging context that outputs to stderr as text.
     pub fn new_root(log_from: Level) -> Self {
-        return Self(Arc::new(Log_ {
-            parent: None,
-            attrs: HashMap::new(),
-            log_from: Some(log_from),
-        }));
+        return Self::new_root_with_format(log_from, TextFormat::new());
+    }
+
+    /// Create a new logging context that outputs to stderr in the specified format,
+    /// ex: `JsonFormat`.
+    pub fn new_root_with_format(log_from: Level, format: impl Format + 'static) -> Self {
+        return Self::new_root_with_sink(log_from, StderrSink::new(format));
+    }
+
+    /// Create a new logging context that outputs to the specified sink.
+    pub fn new_root_with_sink(log_from: Level, sink: impl Sink + 'static) -> Self {
+        return Self::new_root_builder().sink(log_from, sink).build();
+    }
+
+    /// Create a new logging context that captures events in memory, for checking what
+    /// was logged in tests.
+    pub fn new_capture(log_from: Level) -> (Self, Captured) {
+        let captured = Captured::new();
+        return (Self::new_root_with_sink(log_from, captured.clone()), captured);
+    }
+
+    /// Start configuring a new logging context, for instance with multiple sinks with
+    /// different minimum levels.  Use like `Log::new_root_builder().sink(INFO,
+    /// StderrSink::new(TextFormat::new())).sink(DEBUG, file_sink).build()`.
+    pub fn new_root_builder() -> RootBuilder {
+        return RootBuilder {
+            sinks: vec![],
+            clock: None,
+            locations: false,
+            backtraces: false,
+            rate_limit: None,
+        };
     }
 
     /// Create a new `Log` that inherits attributes from the base logging context.  Use
     /// like `let new_log = log.fork(ea!(newkey = newvalue, ...));`.
-    pub fn fork(&self, attrs: impl Fn(&mut HashMap<&'static str, String>) -> ()) -> Self {
-        let mut new_attrs = HashMap::new();
+    pub fn fork(&self, attrs: impl Fn(&mut Attrs) -> ()) -> Self {
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Self(Arc::new(Log_ {
             parent: Some(self.clone()),
             attrs: new_attrs,
-            log_from: self.0.log_from,
+            root: self.0.root.clone(),
         }));
     }
 
     /// Like `fork` but also increase the minimum log level.
-    pub fn fork_with_log_from(&self, log_from: Level, attrs: impl Fn(&mut HashMap<&'static str, String>) -> ()) -> Self {
-        let mut new_attrs = HashMap::new();
+    pub fn fork_with_log_from(&self, log_from: Level, attrs: impl Fn(&mut Attrs) -> ()) -> Self {
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Self(Arc::new(Log_ {
             parent: Some(self.clone()),
             attrs: new_attrs,
-            log_from: self.0.log_from.map(|x| x.max(log_from)),
+            root: self.0.root.as_ref().map(|r| LogRoot {
+                log_from: r.log_from.max(log_from),
+                config: r.config.clone(),
+                rate_limit: r.rate_limit.clone(),
+            }),
+        }));
+    }
+
+    /// Like `fork` but rate limit events logged with the new context and its forks,
+    /// replacing any limit from the base context.  See `RootBuilder::rate_limit`.
+    pub fn fork_with_rate_limit(
+        &self,
+        max: usize,
+        interval: Duration,
+        attrs: impl Fn(&mut Attrs) -> (),
+    ) -> Self {
+        let mut new_attrs = Attrs::new();
+        attrs(&mut new_attrs);
+        return Self(Arc::new(Log_ {
+            parent: Some(self.clone()),
+            attrs: new_attrs,
+            root: self.0.root.as_ref().map(|r| LogRoot {
+                log_from: r.log_from,
+                config: r.config.clone(),
+                rate_limit: Some(RateLimiter::new(max, interval, r.config.clone())),
+            }),
         }));
     }
 
     /// Log a message.  The message will only be rendered and output if any of the
     /// specified flags are set.
+    #[track_caller]
     pub fn log(&self, level: Level, message: impl ToString) {
         self.log_with(level, message, ea!());
     }
 
-    fn should_log(&self, level: Level) -> Option<Level> {
+    /// Get the value of an attribute set on this context or inherited from a parent
+    /// context.
+    pub fn get_attr(&self, key: &str) -> Option<&Value> {
+        let mut at = Some(self);
+        while let Some(at1) = at {
+            if let Some(value) = at1.0.attrs.get(key) {
+                return Some(value);
+            }
+            at = at1.0.parent.as_ref();
+        }
+        return None;
+    }
+
+    /// Returns true if events at the level would be output by this context.
+    pub fn enabled(&self, level: Level) -> bool {
+        return self.should_log(level).is_some();
+    }
+
+    fn should_log(&self, level: Level) -> Option<&LogRoot> {
         // Not rooted/context only
-        let Some(log_from) = self.0.log_from else {
+        let Some(root) = &self.0.root else {
             return None;
         };
-        if level < log_from {
+
+        // Fatal events are only output by `fatal`, which exits
+        if level >= FATAL {
+            return None;
+        }
+        if level < root.log_from {
             return None;
         }
-        return Some(level);
+        if !root.config.sinks.iter().any(|s| level >= s.0) {
+            return None;
+        }
+        return Some(root);
     }
 
     /// Log a message.  The attributes will only be evaluated and the message will only
     /// be rendered and output if any of the specified flags are set.
+    #[track_caller]
     pub fn log_with(
         &self,
         level: Level,
         message: impl ToString,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) {
-        let Some(level) = self.should_log(level) else {
+        if self.should_log(level).is_none() {
             return;
-        };
-        self.log_err(level, self.err_with(message, attrs));
+        }
+        self.log_err(level, self.__event_with(message, attrs));
     }
 
     pub fn log_err(&self, level: Level, mut e: Error) {
-        let Some(level) = self.should_log(level) else {
+        let Some(root) = self.should_log(level) else {
             return;
         };
-        let body_style;
-        let label_style;
-        let label;
-        match level.0 {
-            1 => {
-                body_style = Style::new().for_stderr().black().bright();
-                label_style = Style::new().for_stderr().black().bright();
-                label = "DEBUG";
-            },
-            2 => {
-                body_style = Style::new().for_stderr().black();
-                label_style = Style::new().for_stderr().black();
-                label = "INFO";
-            },
-            3 => {
-                body_style = Style::new().for_stderr().black();
-                label_style = Style::new().for_stderr().yellow();
-                label = "WARN";
-            },
-            4 => {
-                body_style = Style::new().for_stderr().black();
-                label_style = Style::new().for_stderr().red();
-                label = "ERROR";
-            },
-            _ => unreachable!(),
+        e.0.context.push(self.clone());
+        let node = root.config.build_event_node(&e);
+        if let Some(rate_limit) = &root.rate_limit {
+            if !rate_limit.check(level, &node) {
+                return;
+            }
         }
+        root.config.write(level, node);
+    }
+
+    /// Log a fatal error to all of this context's sinks (or stderr if not rooted) and
+    /// terminate the program.
+    pub fn fatal(&self, mut e: Error) -> ! {
         e.0.context.push(self.clone());
-        log(body_style, label_style, label, e.build_render_nodes(&HashSet::new()));
+        fatal_with_root(self.0.root.as_ref(), &e)
     }
 
     /// Create a new error including the attributes in this logging context.
+    #[track_caller]
     pub fn err(&self, message: impl ToString) -> Error {
         return Error(Box::new(Error_ {
             message: message.to_string(),
-            attrs: HashMap::new(),
+            attrs: Attrs::new(),
             context: vec![self.clone()],
             causes: vec![],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: self.capture_backtrace(),
+            original: None,
         }));
     }
 
     /// Create a new error including the attributes in this logging context and merging
     /// additional attributes.
-    pub fn err_with(&self, message: impl ToString, attrs: impl Fn(&mut HashMap<&'static str, String>) -> ()) -> Error {
-        let mut new_attrs = HashMap::new();
+    #[track_caller]
+    pub fn err_with(&self, message: impl ToString, attrs: impl Fn(&mut Attrs) -> ()) -> Error {
+        let mut e = self.__event_with(message, attrs);
+        e.0.backtrace = self.capture_backtrace();
+        return e;
+    }
+
+    /// Like `err_with` but never captures a backtrace, for building log events.
+    #[doc(hidden)]
+    #[track_caller]
+    pub fn __event_with(&self, message: impl ToString, attrs: impl Fn(&mut Attrs) -> ()) -> Error {
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Error(Box::new(Error_ {
             message: message.to_string(),
@@ -416,29 +850,48 @@ impl Log {
             context: vec![self.clone()],
             causes: vec![],
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
+    /// Capture a backtrace for a new error if enabled with `RootBuilder::backtraces` or
+    /// by the environment, see `capture_backtrace`.
+    fn capture_backtrace(&self) -> Option<Arc<Backtrace>> {
+        if self.0.root.as_ref().is_some_and(|r| r.config.backtraces) {
+            return Some(Arc::new(Backtrace::force_capture()));
+        }
+        return capture_backtrace();
+    }
+
     /// Create an error from multiple errors, attaching the Log's attributes.
+    #[track_caller]
     pub fn agg_err(&self, message: impl ToString, errs: Vec<Error>) -> Error {
         return Error(Box::new(Error_ {
             message: message.to_string(),
-            attrs: HashMap::new(),
+            attrs: Attrs::new(),
             context: vec![self.clone()],
             causes: errs,
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 
     /// Create an error from multiple errors, attaching the Log's attributes and
     /// additional attributes.
+    #[track_caller]
     pub fn agg_err_with(
         &self,
         message: impl ToString,
         errs: Vec<Error>,
-        attrs: impl Fn(&mut HashMap<&'static str, String>) -> (),
+        attrs: impl Fn(&mut Attrs) -> (),
     ) -> Error {
-        let mut new_attrs = HashMap::new();
+        let mut new_attrs = Attrs::new();
         attrs(&mut new_attrs);
         return Error(Box::new(Error_ {
             message: message.to_string(),
@@ -446,6 +899,106 @@ impl Log {
             context: vec![self.clone()],
             causes: errs,
             incidental: vec![],
+            template: None,
+            location: Some(Location::caller()),
+            backtrace: None,
+            original: None,
         }));
     }
 }
+
+/// Output a fatal error to the sinks of the root, or stderr if there's no root,
+/// and exit.
+pub(crate) fn fatal_with_root(root: Option<&LogRoot>, e: &Error) -> ! {
+    match root {
+        Some(root) => fatal_to(
+            &root.config.sinks.iter().map(|s| s.1.as_ref()).collect::<Vec<_>>(),
+            root.config.now(),
+            root.config.build_event_node(e),
+        ),
+        None => {
+            let mut node = e.build_event_node(&HashSet::new());
+            node.clear_locations();
+            fatal_to(&[&StderrSink::new(TextFormat::new())], Local::now().fixed_offset(), node)
+        },
+    }
+}
+
+pub(crate) fn fatal_to(sinks: &[&dyn Sink], time: DateTime<FixedOffset>, node: EventNode) -> ! {
+    rate_limit::flush_all();
+    background::flush_all();
+    let event = Event {
+        level: FATAL,
+        time: time,
+        node: node,
+    };
+    for sink in sinks {
+        sink.write(&event);
+    }
+    for sink in sinks {
+        sink.flush();
+    }
+    exit(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            ea,
+            ErrContext,
+            Error,
+            Log,
+            ResultContext,
+            INFO,
+        },
+        std::{
+            io::ErrorKind,
+            sync::Arc,
+        },
+    };
+
+    fn not_found() -> std::io::Error {
+        return std::io::Error::new(ErrorKind::NotFound, "missing");
+    }
+
+    #[test]
+    fn downcast_converted() {
+        let e = not_found().context("Error reading");
+        assert_eq!(e.downcast_ref::<std::io::Error>().map(|e| e.kind()), Some(ErrorKind::NotFound));
+        let e = Err::<(), _>(not_found()).context("Error reading").unwrap_err();
+        assert_eq!(e.downcast_ref::<std::io::Error>().map(|e| e.kind()), Some(ErrorKind::NotFound));
+        let e = (|| -> Result<(), Error> {
+            Err(not_found())?;
+            return Ok(());
+        })().unwrap_err();
+        assert_eq!(e.downcast_ref::<std::io::Error>().map(|e| e.kind()), Some(ErrorKind::NotFound));
+        assert!(e.downcast_ref::<std::fmt::Error>().is_none());
+        assert!(crate::err("missing").downcast_ref::<std::io::Error>().is_none());
+    }
+
+    #[test]
+    fn convert_non_send_by_message() {
+        let m = std::sync::Arc::new(std::sync::Mutex::new(()));
+        _ = std::thread::spawn({
+            let m = m.clone();
+            move || {
+                let _guard = m.lock().unwrap();
+                panic!();
+            }
+        }).join();
+        let e = m.lock().map_err(crate::err).context("Error locking").unwrap_err();
+        assert_eq!(e.causes()[0].message(), "poisoned lock: another task failed inside");
+    }
+
+    #[test]
+    fn find_root() {
+        let (log, _captured) = Log::new_capture(INFO);
+        let child = log.fork(ea!(a = 1));
+        assert!(crate::err("x").find_root().is_none());
+        assert!(crate::err("x").stack_context(&Log::new(), "y").find_root().is_none());
+        assert!(child.err("x").find_root().is_some());
+        let e = crate::err("z").also(crate::err("x").stack_context(&child, "y")).context("w");
+        assert!(Arc::ptr_eq(&e.find_root().unwrap().config, &log.0.root.as_ref().unwrap().config));
+    }
+}
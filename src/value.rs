@@ -0,0 +1,237 @@
+use std::{
+    borrow::Cow,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    fmt::Display,
+    time::Duration,
+};
+
+/// An attribute value.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Null,
+    Str(String),
+    I64(i64),
+    U64(u64),
+    F64(#[cfg_attr(feature = "serde", serde(with = "crate::serialize::f64_value"))] f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(v) => return Some(v),
+            _ => return None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(v) => return Some(*v),
+            _ => return None,
+        }
+    }
+
+    /// The value as an `i64` if it's an integer in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::I64(v) => return Some(*v),
+            Value::U64(v) => return i64::try_from(*v).ok(),
+            _ => return None,
+        }
+    }
+
+    /// The value as a `u64` if it's an integer in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::I64(v) => return u64::try_from(*v).ok(),
+            Value::U64(v) => return Some(*v),
+            _ => return None,
+        }
+    }
+
+    /// The value as an `f64` if it's any number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::I64(v) => return Some(*v as f64),
+            Value::U64(v) => return Some(*v as f64),
+            Value::F64(v) => return Some(*v),
+            _ => return None,
+        }
+    }
+}
+
+/// Strings are output without quotes, lists and maps are output as JSON, and bytes
+/// are output as hex.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Str(v) => return f.write_str(v),
+            Value::Bytes(v) => {
+                for b in v {
+                    write!(f, "{:02x}", b)?;
+                }
+                return Ok(());
+            },
+            v => {
+                let mut out = String::new();
+                crate::json::write_value(&mut out, v);
+                return f.write_str(&out);
+            },
+        }
+    }
+}
+
+/// Conversion to an attribute value, used by `ea!` and `Attrs::insert`.  Types that
+/// don't implement this but do implement `Display` are converted to strings by
+/// `ea!`.
+pub trait ToValue {
+    fn to_value(&self) -> Value;
+
+    fn into_value(self) -> Value where Self: Sized {
+        return self.to_value();
+    }
+}
+
+impl ToValue for Value {
+    fn to_value(&self) -> Value {
+        return self.clone();
+    }
+
+    fn into_value(self) -> Value {
+        return self;
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for &T {
+    fn to_value(&self) -> Value {
+        return (*self).to_value();
+    }
+}
+
+macro_rules! to_value_as{
+    ($variant: ident, $as: ty, $($t: ty), *) => {
+        $(impl ToValue for $t {
+            fn to_value(&self) -> Value {
+                return Value::$variant(*self as $as);
+            }
+        }) *
+    };
+}
+
+to_value_as!(I64, i64, i8, i16, i32, i64, isize);
+
+to_value_as!(U64, u64, u8, u16, u32, u64, usize);
+
+to_value_as!(F64, f64, f32, f64);
+
+impl ToValue for bool {
+    fn to_value(&self) -> Value {
+        return Value::Bool(*self);
+    }
+}
+
+impl ToValue for char {
+    fn to_value(&self) -> Value {
+        return Value::Str(self.to_string());
+    }
+}
+
+impl ToValue for str {
+    fn to_value(&self) -> Value {
+        return Value::Str(self.to_string());
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        return Value::Str(self.clone());
+    }
+
+    fn into_value(self) -> Value {
+        return Value::Str(self);
+    }
+}
+
+impl ToValue for Cow<'_, str> {
+    fn to_value(&self) -> Value {
+        return Value::Str(self.to_string());
+    }
+}
+
+/// Durations are converted to floating point seconds.
+impl ToValue for Duration {
+    fn to_value(&self) -> Value {
+        return Value::F64(self.as_secs_f64());
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(v) => return v.to_value(),
+            None => return Value::Null,
+        }
+    }
+}
+
+/// Converted to a list. Use `Value::Bytes` directly for byte strings.
+impl<T: ToValue> ToValue for [T] {
+    fn to_value(&self) -> Value {
+        return Value::List(self.iter().map(|v| v.to_value()).collect());
+    }
+}
+
+impl<T: ToValue> ToValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        return self.as_slice().to_value();
+    }
+}
+
+impl<K: Display, V: ToValue> ToValue for BTreeMap<K, V> {
+    fn to_value(&self) -> Value {
+        return Value::Map(self.iter().map(|(k, v)| (k.to_string(), v.to_value())).collect());
+    }
+}
+
+/// Entries are sorted by key to keep output stable.
+impl<K: Display, V: ToValue, S> ToValue for HashMap<K, V, S> {
+    fn to_value(&self) -> Value {
+        let mut entries = self.iter().map(|(k, v)| (k.to_string(), v.to_value())).collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        return Value::Map(entries);
+    }
+}
+
+// Autoref specialization for `ea!`: `(&ValueWrap(&v)).loga_value()` resolves to
+// `ToValue` if implemented, falling back to `Display`.
+#[doc(hidden)]
+pub struct ValueWrap<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ViaToValue {
+    fn loga_value(&self) -> Value;
+}
+
+impl<T: ToValue + ?Sized> ViaToValue for ValueWrap<'_, T> {
+    fn loga_value(&self) -> Value {
+        return self.0.to_value();
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaDisplay {
+    fn loga_value(&self) -> Value;
+}
+
+impl<T: Display + ?Sized> ViaDisplay for &ValueWrap<'_, T> {
+    fn loga_value(&self) -> Value {
+        return Value::Str(self.0.to_string());
+    }
+}